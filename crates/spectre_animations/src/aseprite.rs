@@ -0,0 +1,323 @@
+//! Imports sprite sheets exported from Aseprite with a JSON sidecar. Sheets should be exported
+//! in the "json-array" format with tags listed, for example
+//!
+//! ```text
+//! aseprite -b enemy_wolf.aseprite --sheet enemy_wolf.png --data enemy_wolf.json --format json-array --list-tags
+//! ```
+//!
+//! Each tag becomes a named `AnimationClip` (lower case) with the per frame durations set in Aseprite.
+//! A sheet without tags gets a single looping "default" clip covering every frame.
+use bevy::{prelude::*, sprite::Rect as SpriteRect};
+use serde::Deserialize;
+use spectre_loaders::{data_loaders::JsonFileLoader, LoadingStatus};
+use std::{collections::HashMap, path::Path};
+
+use crate::{AnimationClip, LoopMode};
+
+pub struct AsepritePlugin;
+
+impl Plugin for AsepritePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<AsepriteSheet>()
+            .add_asset_loader_from_instance::<AsepriteSheet, JsonFileLoader>(
+                JsonFileLoader::from_extensions(vec!["json"]),
+            )
+            .init_resource::<AnimationLibrary>()
+            .add_system(aseprite_loading_system.system());
+    }
+}
+
+#[derive(Deserialize)]
+pub struct AsepriteRect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Deserialize)]
+pub struct AsepriteSize {
+    pub w: f32,
+    pub h: f32,
+}
+
+#[derive(Deserialize)]
+pub struct AsepriteFrame {
+    pub frame: AsepriteRect,
+
+    /// the duration of the frame in milliseconds
+    pub duration: u32,
+}
+
+#[derive(Deserialize)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub from: usize,
+    pub to: usize,
+    pub direction: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AsepriteMeta {
+    pub image: String,
+    pub size: AsepriteSize,
+
+    #[serde(default)]
+    pub frame_tags: Vec<AsepriteTag>,
+}
+
+/// The JSON data file exported alongside an Aseprite sprite sheet
+#[derive(Deserialize)]
+pub struct AsepriteSheet {
+    pub frames: Vec<AsepriteFrame>,
+    pub meta: AsepriteMeta,
+}
+
+impl AsepriteSheet {
+    /// builds a texture atlas with one sprite per exported frame
+    pub fn texture_atlas(&self, texture: Handle<Texture>) -> TextureAtlas {
+        let mut atlas =
+            TextureAtlas::new_empty(texture, Vec2::new(self.meta.size.w, self.meta.size.h));
+
+        for frame in self.frames.iter() {
+            let rect = &frame.frame;
+            atlas.add_texture(SpriteRect {
+                min: Vec2::new(rect.x, rect.y),
+                max: Vec2::new(rect.x + rect.w, rect.y + rect.h),
+            });
+        }
+
+        atlas
+    }
+
+    /// builds a clip for each tag in the sheet, skipping tags outside the exported frames
+    pub fn clips(&self) -> Vec<AnimationClip> {
+        if self.frames.is_empty() {
+            println!("No frames in {}, skipping its clips", self.meta.image);
+            return vec![];
+        }
+
+        if self.meta.frame_tags.is_empty() {
+            return vec![self.clip("default", 0, self.frames.len() - 1, "forward")];
+        }
+
+        self.meta
+            .frame_tags
+            .iter()
+            .filter(|tag| {
+                let is_valid = tag.from <= tag.to && tag.to < self.frames.len();
+                if !is_valid {
+                    println!(
+                        "Tag {} in {} covers frames {}-{} but there are {} frames, skipping",
+                        tag.name,
+                        self.meta.image,
+                        tag.from,
+                        tag.to,
+                        self.frames.len()
+                    );
+                }
+
+                is_valid
+            })
+            .map(|tag| self.clip(&tag.name, tag.from, tag.to, &tag.direction))
+            .collect()
+    }
+
+    fn clip(&self, name: &str, from: usize, to: usize, direction: &str) -> AnimationClip {
+        let loop_mode = match direction {
+            "pingpong" => LoopMode::PingPong,
+            "forward" => LoopMode::Loop,
+            _ => {
+                println!(
+                    "Unsupported direction {} for tag {} in {}, playing forward",
+                    direction, name, self.meta.image
+                );
+                LoopMode::Loop
+            }
+        };
+
+        let durations = self.frames[from..=to]
+            .iter()
+            .map(|frame| frame.duration as f32 / 1000.)
+            .collect::<Vec<_>>();
+
+        let mut clip = AnimationClip::new(&name.to_lowercase(), from, to, durations[0], loop_mode);
+        clip.frame_durations = durations;
+        clip
+    }
+}
+
+/// A resource which stores the clips imported for each texture atlas.
+/// Atlases can also be looked up by the name of the sheet they were imported from
+#[derive(Default)]
+pub struct AnimationLibrary {
+    clips: HashMap<Handle<TextureAtlas>, Vec<AnimationClip>>,
+    atlases: HashMap<String, Handle<TextureAtlas>>,
+}
+
+impl AnimationLibrary {
+    pub fn insert(&mut self, atlas: Handle<TextureAtlas>, clips: Vec<AnimationClip>) {
+        self.clips.insert(atlas, clips);
+    }
+
+    /// registers a name for an atlas, i.e. "flame_wall" for "assets/flame_wall.json"
+    pub fn insert_name(&mut self, name: &str, atlas: Handle<TextureAtlas>) {
+        self.atlases.insert(name.to_string(), atlas);
+    }
+
+    /// gets the atlas imported from the sheet with the given name
+    pub fn atlas(&self, name: &str) -> Option<Handle<TextureAtlas>> {
+        self.atlases.get(name).copied()
+    }
+
+    /// gets a copy of the clips for the given atlas, or an empty list if it hasn't been imported
+    pub fn clips(&self, atlas: Handle<TextureAtlas>) -> Vec<AnimationClip> {
+        match self.clips.get(&atlas) {
+            None => {
+                println!(
+                    "No animation clips have been imported for atlas {:?}",
+                    atlas
+                );
+                Vec::default()
+            }
+            Some(clips) => clips.clone(),
+        }
+    }
+}
+
+/// Tracks the loading of a single Aseprite sheet, the atlas is stored against the given handle
+pub struct AsepriteSheetRequest {
+    /// the path to the JSON sidecar file
+    pub path: String,
+
+    /// the texture the sheet was exported to
+    pub texture: Handle<Texture>,
+
+    /// the handle to store the texture atlas against
+    pub atlas: Handle<TextureAtlas>,
+
+    handle: Option<Handle<AsepriteSheet>>,
+    is_loaded: bool,
+}
+
+/// Converts (sidecar path, texture id, atlas id)
+impl From<(&str, u128, u128)> for AsepriteSheetRequest {
+    fn from(data: (&str, u128, u128)) -> Self {
+        AsepriteSheetRequest {
+            path: String::from(data.0),
+            texture: Handle::from_u128(data.1),
+            atlas: Handle::from_u128(data.2),
+            handle: None,
+            is_loaded: false,
+        }
+    }
+}
+
+/// Spawn on an entity to import Aseprite sheets. The entity is despawned once they are all loaded
+pub struct LoadAsepriteSheets {
+    pub sheets: Vec<AsepriteSheetRequest>,
+}
+
+fn aseprite_loading_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    sheets: Res<Assets<AsepriteSheet>>,
+    mut texture_atlases: ResMut<Assets<TextureAtlas>>,
+    mut library: ResMut<AnimationLibrary>,
+    mut loading_status: ResMut<LoadingStatus>,
+    mut loaders: Query<(Entity, &mut LoadAsepriteSheets)>,
+) {
+    for (entity, mut loader) in &mut loaders.iter() {
+        for request in loader.sheets.iter_mut() {
+            if request.handle.is_none() {
+                request.handle = Some(
+                    asset_server
+                        .load::<AsepriteSheet, _>(&request.path)
+                        .unwrap(),
+                );
+                loading_status.items_to_load += 1;
+                continue;
+            }
+
+            match sheets.get(&request.handle.unwrap()) {
+                None => continue,
+                Some(sheet) => {
+                    texture_atlases.set(request.atlas, sheet.texture_atlas(request.texture));
+                    library.insert(request.atlas, sheet.clips());
+                    match Path::new(&request.path).file_stem() {
+                        None => {}
+                        Some(name) => library.insert_name(&name.to_string_lossy(), request.atlas),
+                    };
+                    request.is_loaded = true;
+                    loading_status.items_loaded += 1;
+                }
+            };
+        }
+
+        loader.sheets.retain(|request| !request.is_loaded);
+        if loader.sheets.is_empty() {
+            commands.despawn(entity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"{
+        "frames": [
+            { "frame": { "x": 0, "y": 0, "w": 32, "h": 32 }, "duration": 750 },
+            { "frame": { "x": 32, "y": 0, "w": 32, "h": 32 }, "duration": 250 },
+            { "frame": { "x": 64, "y": 0, "w": 32, "h": 32 }, "duration": 100 },
+            { "frame": { "x": 96, "y": 0, "w": 32, "h": 32 }, "duration": 100 }
+        ],
+        "meta": {
+            "image": "character3.png",
+            "size": { "w": 128, "h": 32 },
+            "frameTags": [
+                { "name": "Idle", "from": 0, "to": 1, "direction": "forward" },
+                { "name": "Dead", "from": 2, "to": 3, "direction": "pingpong" }
+            ]
+        }
+    }"#;
+
+    #[test]
+    fn converts_tags_to_clips() {
+        let sheet: AsepriteSheet = serde_json::from_str(SHEET).unwrap();
+        let clips = sheet.clips();
+
+        assert_eq!(clips.len(), 2);
+        assert_eq!(clips[0].name, "idle");
+        assert_eq!(clips[0].loop_mode, LoopMode::Loop);
+        assert_eq!(clips[0].frame_durations, vec![0.75, 0.25]);
+        assert_eq!(clips[1].name, "dead");
+        assert_eq!((clips[1].first_frame, clips[1].last_frame), (2, 3));
+        assert_eq!(clips[1].loop_mode, LoopMode::PingPong);
+    }
+
+    #[test]
+    fn untagged_sheets_have_a_default_clip() {
+        let mut sheet: AsepriteSheet = serde_json::from_str(SHEET).unwrap();
+        sheet.meta.frame_tags.clear();
+        let clips = sheet.clips();
+
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].name, "default");
+        assert_eq!((clips[0].first_frame, clips[0].last_frame), (0, 3));
+    }
+
+    #[test]
+    fn skips_tags_outside_the_frames() {
+        let mut sheet: AsepriteSheet = serde_json::from_str(SHEET).unwrap();
+        sheet.meta.frame_tags[0].to = 4;
+        let clips = sheet.clips();
+
+        assert_eq!(clips.len(), 1);
+        assert_eq!(clips[0].name, "dead");
+
+        sheet.frames.clear();
+        assert!(sheet.clips().is_empty());
+    }
+}
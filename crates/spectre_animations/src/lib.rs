@@ -1,6 +1,14 @@
 use bevy::prelude::*;
+use spectre_time::GameTime;
+
+pub mod aseprite;
+pub mod state_machine;
+pub mod vfx;
 
 pub mod prelude {
+    pub use crate::aseprite::*;
+    pub use crate::state_machine::*;
+    pub use crate::vfx::*;
     pub use crate::*;
 }
 
@@ -8,113 +16,439 @@ pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(animate_sprites.system());
+        app.add_event::<AnimationFrameEvent>()
+            .add_event::<AnimationFinishedEvent>()
+            .add_plugin(aseprite::AsepritePlugin)
+            .add_plugin(vfx::VfxPlugin)
+            .add_system(state_machine::animation_state_machine_system.system())
+            .add_system(animate_sprites.system());
+    }
+}
+
+/// What a clip does once it reaches its last frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMode {
+    /// jump back to the first frame
+    Loop,
+    /// stop playing on the last frame
+    Once,
+    /// play backwards to the first frame, then forwards again
+    PingPong,
+    /// stay on the last frame until another clip is set
+    HoldLast,
+}
+
+/// A named range of frames in a texture atlas
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    pub name: String,
+    pub first_frame: usize,
+    pub last_frame: usize,
+    pub frame_duration: f32,
+    pub loop_mode: LoopMode,
+
+    /// optional per frame durations (i.e. imported from Aseprite), `frame_duration` is used if empty
+    pub frame_durations: Vec<f32>,
+
+    /// events raised when the given frame is shown, the frame is relative to `first_frame`
+    pub events: Vec<(usize, String)>,
+}
+
+impl AnimationClip {
+    pub fn new(
+        name: &str,
+        first_frame: usize,
+        last_frame: usize,
+        frame_duration: f32,
+        loop_mode: LoopMode,
+    ) -> Self {
+        AnimationClip {
+            name: name.to_string(),
+            first_frame,
+            last_frame,
+            frame_duration,
+            loop_mode,
+            frame_durations: Vec::default(),
+            events: Vec::default(),
+        }
+    }
+
+    /// gets how long the given atlas index should be shown for
+    pub fn duration_of(&self, frame_idx: usize) -> f32 {
+        self.frame_durations
+            .get(frame_idx.saturating_sub(self.first_frame))
+            .cloned()
+            .unwrap_or(self.frame_duration)
+    }
+
+    /// adds a named event which is raised whenever the given frame of the clip is shown
+    pub fn with_event(mut self, frame: usize, event: &str) -> Self {
+        self.events.push((frame, event.to_string()));
+        self
     }
 }
 
-pub type AnimationFrameRange = (usize, usize);
+/// Raised when an animation reaches a frame with a named event, i.e. a "hit" frame in an attack
+pub struct AnimationFrameEvent {
+    pub entity: Entity,
+    pub clip: String,
+    pub event: String,
+}
 
+/// Raised when a `LoopMode::Once` or `LoopMode::HoldLast` clip reaches its last frame
+pub struct AnimationFinishedEvent {
+    pub entity: Entity,
+    pub clip: String,
+}
+
+/// The frames entered while stepping an animation forward
+#[derive(Debug, Default, PartialEq)]
+pub struct AnimationStep {
+    /// the atlas indices of the frames that were entered, in order
+    pub entered_frames: Vec<usize>,
+
+    /// true if a Once or HoldLast clip reached its last frame during the step
+    pub finished: bool,
+}
+
+/// Plays clips from a texture atlas. Animations are driven by `GameTime`,
+/// so they pause with the game, and can be sped up or slowed down per entity
 pub struct AnimationState {
-    pub animations: Vec<AnimationFrameRange>,
-    pub current_animation: usize,
+    pub clips: Vec<AnimationClip>,
+    pub current_clip: usize,
+
+    /// the atlas index of the current frame
     pub current_idx: usize,
+
+    /// game time spent on the current frame so far
+    pub frame_timer: f32,
+
+    /// multiplier applied to game time, i.e. 2.0 plays at double speed
+    pub speed: f32,
+
     pub is_playing: bool,
-    pub one_shot: bool,
+    pub is_reversing: bool,
 }
 
 impl Default for AnimationState {
     fn default() -> Self {
         AnimationState {
-            animations: Vec::default(),
-            current_animation: 0,
+            clips: Vec::default(),
+            current_clip: 0,
             current_idx: 0,
+            frame_timer: 0.,
+            speed: 1.,
             is_playing: false,
-            one_shot: false,
+            is_reversing: false,
         }
     }
 }
 
 impl AnimationState {
-    // sets the animation on an animation state and moves to the first frame
-    pub fn set_animation(&mut self, animation_idx: usize) -> bool {
-        if animation_idx >= self.animations.len() {
+    /// Returns the clip that is currently playing
+    pub fn clip(&self) -> &AnimationClip {
+        &self.clips[self.current_clip]
+    }
+
+    // sets the animation on an animation state and restarts it from the first frame
+    pub fn set_animation(&mut self, name: &str) -> bool {
+        let clip_idx = self.clips.iter().position(|clip| clip.name == name);
+        if clip_idx.is_none() {
             println!(
-                "Unknown animation {}, max idx {}",
-                animation_idx,
-                self.animations.len()
+                "Unknown animation {}, available clips {:?}",
+                name,
+                self.clips.iter().map(|clip| &clip.name).collect::<Vec<_>>()
             );
             return false;
         }
 
-        self.current_animation = animation_idx;
-        self.current_idx = self.animations[animation_idx].0;
+        self.current_clip = clip_idx.unwrap();
+        self.current_idx = self.clip().first_frame;
+        self.frame_timer = 0.;
+        self.is_playing = true;
+        self.is_reversing = false;
         true
     }
 
-    // increments the animation frame
-    pub fn incr(&mut self) -> u32 {
-        if self.current_idx == self.animations[self.current_animation].1 {
-            self.current_idx = self.animations[self.current_animation].0;
-        } else {
+    /// advances the animation by the given amount of game time, moving through as many
+    /// frames as have elapsed (scaled by the speed multiplier)
+    pub fn step(&mut self, delta: f32) -> AnimationStep {
+        let mut result = AnimationStep::default();
+        if !self.is_playing || self.clips.is_empty() {
+            return result;
+        }
+
+        self.frame_timer += delta * self.speed;
+
+        loop {
+            let (duration, last, loop_mode) = {
+                let clip = self.clip();
+                (
+                    clip.duration_of(self.current_idx),
+                    clip.last_frame,
+                    clip.loop_mode,
+                )
+            };
+
+            // nothing left to play while holding the last frame
+            if loop_mode == LoopMode::HoldLast && self.current_idx == last {
+                self.frame_timer = 0.;
+                break;
+            }
+
+            if duration <= 0. || self.frame_timer < duration {
+                break;
+            }
+
+            self.frame_timer -= duration;
+
+            let prev = self.current_idx;
+            result.finished |= self.incr();
+            if self.current_idx != prev {
+                result.entered_frames.push(self.current_idx);
+            }
+
+            if !self.is_playing {
+                self.frame_timer = 0.;
+                break;
+            }
+        }
+
+        result
+    }
+
+    /// increments the animation frame, returning true if a Once or HoldLast clip has just finished
+    pub fn incr(&mut self) -> bool {
+        let (first, last, loop_mode) = {
+            let clip = self.clip();
+            (clip.first_frame, clip.last_frame, clip.loop_mode)
+        };
+
+        if self.is_reversing {
+            if self.current_idx <= first {
+                self.is_reversing = false;
+                self.current_idx = (first + 1).min(last);
+            } else {
+                self.current_idx -= 1;
+            }
+
+            return false;
+        }
+
+        if self.current_idx < last {
             self.current_idx += 1;
+            return self.current_idx == last && loop_mode == LoopMode::HoldLast;
         }
 
-        self.current_idx as u32
+        match loop_mode {
+            LoopMode::Loop => {
+                self.current_idx = first;
+                false
+            }
+            LoopMode::PingPong => {
+                self.is_reversing = true;
+                self.current_idx = last.max(first + 1) - 1;
+                false
+            }
+            LoopMode::Once => {
+                self.is_playing = false;
+                true
+            }
+            LoopMode::HoldLast => false,
+        }
     }
 
-    pub fn get_frame_index(&self) -> u32 {
-        if !self.is_playing {
-            return 0;
+    /// returns true if a Once clip has stopped, or a HoldLast clip is holding its last frame
+    pub fn is_finished(&self) -> bool {
+        if self.clips.is_empty() {
+            return false;
         }
 
-        return (self.animations[self.current_animation].0 + self.current_idx) as u32;
+        let clip = self.clip();
+        match clip.loop_mode {
+            LoopMode::Once => !self.is_playing,
+            LoopMode::HoldLast => self.current_idx == clip.last_frame,
+            _ => false,
+        }
+    }
+
+    /// gets the atlas index of the current frame
+    pub fn get_frame_index(&self) -> u32 {
+        self.current_idx as u32
     }
 }
 
 fn animate_sprites(
-    mut commands: Commands,
-    mut query: Query<(
-        Entity,
-        &mut Timer,
-        &mut TextureAtlasSprite,
-        &mut AnimationState,
-    )>,
+    game_time: Res<GameTime>,
+    mut frame_events: ResMut<Events<AnimationFrameEvent>>,
+    mut finished_events: ResMut<Events<AnimationFinishedEvent>>,
+    mut query: Query<(Entity, &mut TextureAtlasSprite, &mut AnimationState)>,
 ) {
-    for (entity, timer, mut sprite, mut state) in &mut query.iter() {
-        if timer.finished {
-            let prev = state.current_idx as u32;
-            sprite.index = state.incr();
+    for (entity, mut sprite, mut state) in &mut query.iter() {
+        if state.clips.is_empty() {
+            continue;
+        }
 
-            if state.one_shot && sprite.index < prev {
-                commands.despawn(entity);
+        let step = state.step(game_time.delta);
+        sprite.index = state.get_frame_index();
+
+        let clip = state.clip();
+        for entered in step.entered_frames.iter() {
+            for (frame, event) in clip.events.iter() {
+                if clip.first_frame + frame == *entered {
+                    frame_events.send(AnimationFrameEvent {
+                        entity,
+                        clip: clip.name.clone(),
+                        event: event.clone(),
+                    });
+                }
             }
         }
+
+        if step.finished {
+            finished_events.send(AnimationFinishedEvent {
+                entity,
+                clip: clip.name.clone(),
+            });
+        }
     }
 }
 
+/// Spawns an animated sprite sheet playing the first of the given clips. Without any clips
+/// the first frame of the atlas is shown
 pub fn spawn_animated_spritesheet(
     commands: &mut Commands,
     texture_atlas_handle: Handle<TextureAtlas>,
-    frame_duration: f32,
-    animation_frames: Vec<(usize, usize)>,
+    clips: Vec<AnimationClip>,
     location: Vec3,
-    one_shot: bool,
 ) -> &mut Commands {
+    let first_frame = match clips.first() {
+        None => {
+            println!("No animation clips to play, showing the first frame");
+            0
+        }
+        Some(clip) => clip.first_frame,
+    };
+    let is_playing = !clips.is_empty();
+
     commands
         .spawn(SpriteSheetComponents {
             texture_atlas: texture_atlas_handle,
+            sprite: TextureAtlasSprite::new(first_frame as u32),
             transform: Transform::from_scale(1.0).with_translation(location),
             ..Default::default()
         })
-        .with(Timer::from_seconds(frame_duration, true))
         .with(AnimationState {
-            animations: animation_frames,
-            current_animation: 0,
-            current_idx: 0,
-            is_playing: true,
-            one_shot,
+            clips,
+            current_clip: 0,
+            current_idx: first_frame,
+            frame_timer: 0.,
+            speed: 1.,
+            is_playing,
+            is_reversing: false,
         });
 
     // commands.current_entity().unwrap()
     commands
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(clip: AnimationClip) -> AnimationState {
+        AnimationState {
+            current_idx: clip.first_frame,
+            clips: vec![clip],
+            is_playing: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn loops_back_to_first_frame() {
+        let mut state = state_with(AnimationClip::new("walk", 4, 6, 0.5, LoopMode::Loop));
+
+        assert_eq!(state.step(0.4).entered_frames, Vec::<usize>::new());
+        assert_eq!(state.step(0.1).entered_frames, vec![5]);
+        assert_eq!(state.step(1.0).entered_frames, vec![6, 4]);
+        assert_eq!(state.get_frame_index(), 4);
+    }
+
+    #[test]
+    fn once_finishes_and_stops() {
+        let mut state = state_with(AnimationClip::new("hit", 0, 2, 0.1, LoopMode::Once));
+
+        let step = state.step(0.35);
+        assert_eq!(step.entered_frames, vec![1, 2]);
+        assert!(step.finished);
+        assert!(!state.is_playing);
+        assert_eq!(state.step(1.0), AnimationStep::default());
+        assert_eq!(state.get_frame_index(), 2);
+    }
+
+    #[test]
+    fn ping_pong_reverses_at_each_end() {
+        let mut state = state_with(AnimationClip::new("idle", 0, 2, 1.0, LoopMode::PingPong));
+
+        let step = state.step(6.0);
+        assert_eq!(step.entered_frames, vec![1, 2, 1, 0, 1, 2]);
+        assert!(!step.finished);
+    }
+
+    #[test]
+    fn hold_last_stays_on_last_frame() {
+        let mut state = state_with(AnimationClip::new("dead", 2, 3, 0.5, LoopMode::HoldLast));
+
+        let step = state.step(0.5);
+        assert_eq!(step.entered_frames, vec![3]);
+        assert!(step.finished);
+
+        assert_eq!(state.step(5.0), AnimationStep::default());
+        assert_eq!(state.get_frame_index(), 3);
+        assert!(state.is_playing);
+    }
+
+    #[test]
+    fn speed_multiplier_scales_game_time() {
+        let mut state = state_with(AnimationClip::new("walk", 0, 3, 0.5, LoopMode::Loop));
+        state.speed = 2.;
+
+        assert_eq!(state.step(0.5).entered_frames, vec![1, 2]);
+    }
+
+    #[test]
+    fn uses_per_frame_durations() {
+        let mut clip = AnimationClip::new("attack", 0, 2, 0.1, LoopMode::Loop);
+        clip.frame_durations = vec![0.1, 0.5, 0.1];
+        let mut state = state_with(clip);
+
+        assert_eq!(state.step(0.2).entered_frames, vec![1]);
+        assert_eq!(state.step(0.3).entered_frames, Vec::<usize>::new());
+        assert_eq!(state.step(0.25).entered_frames, vec![2, 0]);
+    }
+
+    #[test]
+    fn does_not_advance_with_zero_delta() {
+        let mut state = state_with(AnimationClip::new("walk", 0, 3, 0.5, LoopMode::Loop));
+
+        assert_eq!(state.step(0.).entered_frames, Vec::<usize>::new());
+        assert_eq!(state.get_frame_index(), 0);
+    }
+
+    #[test]
+    fn set_animation_resets_frame_and_timer() {
+        let mut state = state_with(AnimationClip::new("idle", 0, 1, 0.75, LoopMode::Loop));
+        state
+            .clips
+            .push(AnimationClip::new("dead", 2, 3, 0.75, LoopMode::Loop));
+
+        state.step(1.);
+        assert!(state.set_animation("dead"));
+        assert_eq!(state.get_frame_index(), 2);
+        assert_eq!(state.frame_timer, 0.);
+
+        assert_eq!(state.step(0.5).entered_frames, Vec::<usize>::new());
+        assert!(!state.set_animation("missing"));
+    }
+}
@@ -0,0 +1,311 @@
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use crate::AnimationState;
+
+/// A condition which must be met before a transition is taken
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransitionCondition {
+    /// the named parameter is true
+    IsSet(String),
+    /// the named parameter is false, or has never been set
+    IsNotSet(String),
+    /// the named trigger was fired since the graph was last evaluated
+    Triggered(String),
+    /// the current clip has finished playing (only Once and HoldLast clips finish)
+    ClipFinished,
+}
+
+/// A transition between two states. Transitions are checked in the order they were added
+/// and the first one with all conditions met is taken.
+#[derive(Clone, Debug)]
+pub struct AnimationTransition {
+    /// the state to transition from, or None to transition from any other state
+    pub from: Option<String>,
+    pub to: String,
+    pub conditions: Vec<TransitionCondition>,
+
+    /// set to true to allow this transition to cut short a state which isn't interruptible
+    pub interrupts: bool,
+}
+
+impl AnimationTransition {
+    pub fn new(from: &str, to: &str) -> Self {
+        AnimationTransition {
+            from: Some(from.to_string()),
+            to: to.to_string(),
+            conditions: Vec::default(),
+            interrupts: false,
+        }
+    }
+
+    pub fn from_any(to: &str) -> Self {
+        AnimationTransition {
+            from: None,
+            to: to.to_string(),
+            conditions: Vec::default(),
+            interrupts: false,
+        }
+    }
+
+    pub fn when(mut self, parameter: &str) -> Self {
+        self.conditions
+            .push(TransitionCondition::IsSet(parameter.to_string()));
+        self
+    }
+
+    pub fn when_not(mut self, parameter: &str) -> Self {
+        self.conditions
+            .push(TransitionCondition::IsNotSet(parameter.to_string()));
+        self
+    }
+
+    pub fn on_trigger(mut self, trigger: &str) -> Self {
+        self.conditions
+            .push(TransitionCondition::Triggered(trigger.to_string()));
+        self
+    }
+
+    pub fn on_finish(mut self) -> Self {
+        self.conditions.push(TransitionCondition::ClipFinished);
+        self
+    }
+
+    pub fn interrupting(mut self) -> Self {
+        self.interrupts = true;
+        self
+    }
+}
+
+/// A state in the animation graph, which plays the given clip when entered
+#[derive(Clone, Debug)]
+pub struct AnimationGraphState {
+    pub name: String,
+    pub clip: String,
+
+    /// states which aren't interruptible play until their clip finishes,
+    /// unless a transition is marked as `interrupts`
+    pub interruptible: bool,
+}
+
+/// A per-entity animation graph. Gameplay systems set parameters and fire triggers,
+/// and the graph chooses which clip the `AnimationState` on the same entity plays
+pub struct AnimationStateMachine {
+    pub states: Vec<AnimationGraphState>,
+    pub transitions: Vec<AnimationTransition>,
+    pub current: String,
+
+    parameters: HashMap<String, bool>,
+    triggers: HashSet<String>,
+
+    /// the clip to switch to the next time the graph is evaluated
+    pending_clip: Option<String>,
+}
+
+impl AnimationStateMachine {
+    /// creates a graph which starts in the given state, playing a clip of the same name
+    pub fn new(initial_state: &str) -> Self {
+        AnimationStateMachine {
+            states: Vec::default(),
+            transitions: Vec::default(),
+            current: initial_state.to_string(),
+            parameters: HashMap::default(),
+            triggers: HashSet::default(),
+            pending_clip: None,
+        }
+        .with_state(initial_state, initial_state)
+    }
+
+    /// adds (or replaces) a state which plays the given clip
+    pub fn with_state(self, name: &str, clip: &str) -> Self {
+        self.with_graph_state(AnimationGraphState {
+            name: name.to_string(),
+            clip: clip.to_string(),
+            interruptible: true,
+        })
+    }
+
+    /// adds (or replaces) a state which plays the given clip through to the end before leaving
+    pub fn with_uninterruptible_state(self, name: &str, clip: &str) -> Self {
+        self.with_graph_state(AnimationGraphState {
+            name: name.to_string(),
+            clip: clip.to_string(),
+            interruptible: false,
+        })
+    }
+
+    fn with_graph_state(mut self, state: AnimationGraphState) -> Self {
+        self.states.retain(|existing| existing.name != state.name);
+        if state.name == self.current {
+            self.pending_clip = Some(state.clip.clone());
+        }
+
+        self.states.push(state);
+        self
+    }
+
+    pub fn with_transition(mut self, transition: AnimationTransition) -> Self {
+        self.transitions.push(transition);
+        self
+    }
+
+    pub fn set(&mut self, parameter: &str, value: bool) {
+        self.parameters.insert(parameter.to_string(), value);
+    }
+
+    pub fn is_set(&self, parameter: &str) -> bool {
+        *self.parameters.get(parameter).unwrap_or(&false)
+    }
+
+    /// fires a trigger, which is cleared the next time the graph is evaluated
+    pub fn trigger(&mut self, trigger: &str) {
+        self.triggers.insert(trigger.to_string());
+    }
+
+    fn get_state(&self, name: &str) -> Option<&AnimationGraphState> {
+        self.states.iter().find(|state| state.name == name)
+    }
+
+    fn is_met(&self, condition: &TransitionCondition, clip_finished: bool) -> bool {
+        match condition {
+            TransitionCondition::IsSet(parameter) => self.is_set(parameter),
+            TransitionCondition::IsNotSet(parameter) => !self.is_set(parameter),
+            TransitionCondition::Triggered(trigger) => self.triggers.contains(trigger),
+            TransitionCondition::ClipFinished => clip_finished,
+        }
+    }
+
+    /// checks the transitions out of the current state, returning the clip to play if the state changed
+    pub fn evaluate(&mut self, clip_finished: bool) -> Option<String> {
+        if self.pending_clip.is_some() {
+            return self.pending_clip.take();
+        }
+
+        let interruptible = clip_finished
+            || match self.get_state(&self.current) {
+                None => true,
+                Some(state) => state.interruptible,
+            };
+
+        let next = self.transitions.iter().find(|transition| {
+            let from_current = match &transition.from {
+                Some(from) => *from == self.current,
+                None => transition.to != self.current,
+            };
+
+            from_current
+                && (interruptible || transition.interrupts)
+                && transition
+                    .conditions
+                    .iter()
+                    .all(|condition| self.is_met(condition, clip_finished))
+        });
+
+        let next_clip = match next {
+            None => None,
+            Some(transition) => match self.get_state(&transition.to) {
+                None => {
+                    println!("Unknown animation graph state {}", transition.to);
+                    None
+                }
+                Some(state) => Some((state.name.clone(), state.clip.clone())),
+            },
+        };
+
+        self.triggers.clear();
+
+        match next_clip {
+            None => None,
+            Some((state, clip)) => {
+                self.current = state;
+                Some(clip)
+            }
+        }
+    }
+}
+
+pub(crate) fn animation_state_machine_system(
+    mut query: Query<(&mut AnimationStateMachine, &mut AnimationState)>,
+) {
+    for (mut machine, mut state) in &mut query.iter() {
+        match machine.evaluate(state.is_finished()) {
+            None => {}
+            Some(clip) => {
+                state.set_animation(&clip);
+            }
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn player_graph() -> AnimationStateMachine {
+        let mut machine = AnimationStateMachine::new("idle")
+            .with_state("moving", "walk")
+            .with_uninterruptible_state("hurt", "hurt")
+            .with_state("dead", "dead")
+            .with_transition(
+                AnimationTransition::from_any("dead")
+                    .when("incapacitated")
+                    .interrupting(),
+            )
+            .with_transition(AnimationTransition::new("dead", "idle").when_not("incapacitated"))
+            .with_transition(AnimationTransition::from_any("hurt").on_trigger("hurt"))
+            .with_transition(AnimationTransition::new("hurt", "idle").on_finish())
+            .with_transition(AnimationTransition::new("idle", "moving").when("moving"))
+            .with_transition(AnimationTransition::new("moving", "idle").when_not("moving"));
+
+        // play the initial clip
+        assert_eq!(machine.evaluate(false), Some("idle".to_string()));
+        machine
+    }
+
+    #[test]
+    fn follows_parameters() {
+        let mut machine = player_graph();
+        assert_eq!(machine.evaluate(false), None);
+
+        machine.set("moving", true);
+        assert_eq!(machine.evaluate(false), Some("walk".to_string()));
+        assert_eq!(machine.current, "moving");
+
+        machine.set("moving", false);
+        assert_eq!(machine.evaluate(false), Some("idle".to_string()));
+    }
+
+    #[test]
+    fn plays_hurt_once_then_returns_to_idle() {
+        let mut machine = player_graph();
+
+        machine.trigger("hurt");
+        assert_eq!(machine.evaluate(false), Some("hurt".to_string()));
+
+        // not interruptible, and the trigger was consumed
+        machine.set("moving", true);
+        machine.trigger("hurt");
+        assert_eq!(machine.evaluate(false), None);
+        assert_eq!(machine.current, "hurt");
+
+        assert_eq!(machine.evaluate(true), Some("idle".to_string()));
+        assert_eq!(machine.evaluate(false), Some("walk".to_string()));
+    }
+
+    #[test]
+    fn interrupting_transitions_cut_states_short() {
+        let mut machine = player_graph();
+
+        machine.trigger("hurt");
+        machine.evaluate(false);
+
+        machine.set("incapacitated", true);
+        assert_eq!(machine.evaluate(false), Some("dead".to_string()));
+
+        // any state transitions don't loop back into the same state
+        assert_eq!(machine.evaluate(false), None);
+
+        machine.set("incapacitated", false);
+        assert_eq!(machine.evaluate(false), Some("idle".to_string()));
+    }
+}
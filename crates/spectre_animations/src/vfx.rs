@@ -0,0 +1,305 @@
+//! A pooled visual effects spawner. Effects are defined in data files and requested by name
+//! by sending a `SpawnVfxEvent`. Finished effects are hidden and reused for later requests
+//! rather than being despawned.
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_loaders::data_loaders::MergeData;
+use spectre_time::GameTime;
+use std::collections::HashMap;
+
+use crate::{aseprite::AnimationLibrary, AnimationState, LoopMode};
+
+pub struct VfxPlugin;
+
+impl Plugin for VfxPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<VfxDatabase>()
+            .add_event::<SpawnVfxEvent>()
+            .init_resource::<VfxEventListener>()
+            .add_system(vfx_spawn_system.system())
+            .add_system(vfx_update_system.system());
+    }
+}
+
+fn default_scale() -> f32 {
+    1.
+}
+
+fn default_tint() -> (f32, f32, f32, f32) {
+    (1., 1., 1., 1.)
+}
+
+/// Describes how to show an effect
+#[derive(Clone, Deserialize, Serialize)]
+pub struct VfxDefinition {
+    /// the Aseprite sheet to use, i.e. "flame_wall" for "assets/flame_wall.json"
+    pub sheet: String,
+
+    /// the clip in the sheet to play
+    pub clip: String,
+
+    #[serde(default = "default_scale")]
+    pub scale: f32,
+
+    /// an rgba colour to multiply the sprite by
+    #[serde(default = "default_tint")]
+    pub tint: (f32, f32, f32, f32),
+
+    /// how long the effect lasts in seconds (looping the clip),
+    /// or None to play the clip once and then remove the effect
+    #[serde(default)]
+    pub lifetime: Option<f32>,
+
+    /// an offset from the requested position (or the entity the effect is attached to)
+    #[serde(default)]
+    pub offset: (f32, f32, f32),
+}
+
+/// A resource containing every effect that can be spawned, keyed by name
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct VfxDatabase {
+    pub effects: HashMap<String, VfxDefinition>,
+}
+
+impl MergeData for VfxDatabase {
+    fn merge(&mut self, other: &Self) {
+        for (name, effect) in other.effects.iter() {
+            self.effects.insert(name.clone(), effect.clone());
+        }
+    }
+}
+
+/// Send to show an effect by name
+pub struct SpawnVfxEvent {
+    pub name: String,
+
+    /// the world position, or the offset from the `attach_to` entity
+    pub position: Vec3,
+
+    /// an entity for the effect to follow, the effect is removed if the entity is despawned
+    pub attach_to: Option<Entity>,
+}
+
+impl SpawnVfxEvent {
+    /// shows an effect at a fixed position
+    pub fn at(name: &str, position: Vec3) -> Self {
+        SpawnVfxEvent {
+            name: name.to_string(),
+            position,
+            attach_to: None,
+        }
+    }
+
+    /// shows an effect which follows an entity around
+    pub fn attached(name: &str, entity: Entity, offset: Vec3) -> Self {
+        SpawnVfxEvent {
+            name: name.to_string(),
+            position: offset,
+            attach_to: Some(entity),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct VfxEventListener {
+    pub spawn_reader: EventReader<SpawnVfxEvent>,
+}
+
+/// A pooled effect entity, hidden when not active
+#[derive(Clone)]
+pub struct VfxInstance {
+    pub is_active: bool,
+    pub attach_to: Option<Entity>,
+    pub offset: Vec3,
+
+    /// the game time the effect is removed at, or None to remove it when the clip finishes
+    pub expires_at: Option<f32>,
+}
+
+fn vfx_spawn_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    database: Res<VfxDatabase>,
+    library: Res<AnimationLibrary>,
+    events: Res<Events<SpawnVfxEvent>>,
+    mut listener: ResMut<VfxEventListener>,
+    mut instances: Query<(
+        &mut VfxInstance,
+        &mut Handle<TextureAtlas>,
+        &mut TextureAtlasSprite,
+        &mut AnimationState,
+        &mut Transform,
+        &mut Draw,
+    )>,
+) {
+    for request in listener.spawn_reader.iter(&events) {
+        let definition = match database.effects.get(&request.name) {
+            None => {
+                println!("Unknown effect {}, skipping", request.name);
+                continue;
+            }
+            Some(definition) => definition,
+        };
+
+        let atlas = match library.atlas(&definition.sheet) {
+            None => {
+                println!(
+                    "Unknown sheet {} for effect {}",
+                    definition.sheet, request.name
+                );
+                continue;
+            }
+            Some(atlas) => atlas,
+        };
+
+        let mut clip = match library
+            .clips(atlas)
+            .into_iter()
+            .find(|clip| clip.name == definition.clip)
+        {
+            None => {
+                println!(
+                    "Unknown clip {} for effect {}",
+                    definition.clip, request.name
+                );
+                continue;
+            }
+            Some(clip) => clip,
+        };
+
+        clip.loop_mode = match definition.lifetime {
+            None => LoopMode::Once,
+            Some(_) => LoopMode::Loop,
+        };
+
+        let offset = request.position + Vec3::from(definition.offset);
+        let color = Color::rgba(
+            definition.tint.0,
+            definition.tint.1,
+            definition.tint.2,
+            definition.tint.3,
+        );
+        let vfx = VfxInstance {
+            is_active: true,
+            attach_to: request.attach_to,
+            offset,
+            expires_at: definition
+                .lifetime
+                .map(|lifetime| game_time.elapsed_time + lifetime),
+        };
+        let animation = AnimationState {
+            current_idx: clip.first_frame,
+            clips: vec![clip],
+            is_playing: true,
+            ..Default::default()
+        };
+        // attached effects are moved into place by the update system
+        let transform = Transform::from_translation(offset).with_scale(definition.scale);
+
+        // reuse a pooled effect if one is free
+        let mut reused = false;
+        for (mut instance, mut texture_atlas, mut sprite, mut state, mut tx, mut draw) in
+            &mut instances.iter()
+        {
+            if instance.is_active {
+                continue;
+            }
+
+            *instance = vfx.clone();
+            *texture_atlas = atlas;
+            sprite.index = animation.get_frame_index();
+            sprite.color = color;
+            *state = AnimationState {
+                clips: animation.clips.clone(),
+                ..animation
+            };
+            *tx = transform;
+            draw.is_visible = true;
+
+            reused = true;
+            break;
+        }
+
+        if reused {
+            continue;
+        }
+
+        commands
+            .spawn(SpriteSheetComponents {
+                texture_atlas: atlas,
+                sprite: TextureAtlasSprite {
+                    index: animation.get_frame_index(),
+                    color,
+                },
+                transform,
+                ..Default::default()
+            })
+            .with(animation)
+            .with(vfx);
+    }
+}
+
+/// Moves attached effects and returns finished effects to the pool
+fn vfx_update_system(
+    game_time: Res<GameTime>,
+    mut instances: Query<(
+        &mut VfxInstance,
+        &mut AnimationState,
+        &mut Transform,
+        &mut Draw,
+    )>,
+    targets: Query<Without<VfxInstance, &Transform>>,
+) {
+    for (mut instance, mut state, mut transform, mut draw) in &mut instances.iter() {
+        if !instance.is_active {
+            continue;
+        }
+
+        let mut is_finished = match instance.expires_at {
+            None => state.is_finished(),
+            Some(expiry) => expiry <= game_time.elapsed_time,
+        };
+
+        match instance.attach_to {
+            None => {}
+            Some(target) => match targets.get::<Transform>(target) {
+                Err(_) => is_finished = true,
+                Ok(target_tx) => {
+                    transform.set_translation(target_tx.translation() + instance.offset);
+                }
+            },
+        };
+
+        if is_finished {
+            instance.is_active = false;
+            instance.attach_to = None;
+            state.is_playing = false;
+            draw.is_visible = false;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_optional_fields() {
+        let database: VfxDatabase = ron::de::from_str(
+            r#"(effects: {
+                "heal": (sheet: "heal", clip: "effect"),
+                "aura": (sheet: "heal", clip: "effect", scale: 2.0, lifetime: Some(5.0), offset: (0.0, 10.0, 1.0)),
+            })"#,
+        )
+        .unwrap();
+
+        let heal = &database.effects["heal"];
+        assert_eq!(heal.scale, 1.);
+        assert_eq!(heal.tint, (1., 1., 1., 1.));
+        assert_eq!(heal.lifetime, None);
+
+        let aura = &database.effects["aura"];
+        assert_eq!(aura.lifetime, Some(5.));
+        assert_eq!(aura.offset, (0., 10., 1.));
+    }
+}
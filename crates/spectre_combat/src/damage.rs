@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use spectre_random::RNG;
+use std::collections::HashMap;
+
+/// The type of damage an attack deals, each type is reduced by a different armour.
+/// Pure damage is physical and is reduced by base armour
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+pub enum DamageType {
+    Pure,
+    Poison,
+    Shock,
+    Ice,
+    Fire,
+}
+
+impl Default for DamageType {
+    fn default() -> Self {
+        DamageType::Pure
+    }
+}
+
+/// Percentage modifiers applied to each type of damage after armour. A positive modifier is
+/// a resistance (0.25 takes 25% less damage), a negative modifier is a weakness (-0.5 takes
+/// 50% more damage) and 1.0 is an immunity. Types without a modifier take full damage
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Resistances {
+    pub modifiers: HashMap<DamageType, f32>,
+}
+
+impl Resistances {
+    pub fn with(mut self, damage_type: DamageType, modifier: f32) -> Self {
+        self.modifiers.insert(damage_type, modifier);
+        self
+    }
+
+    pub fn immune_to(self, damage_type: DamageType) -> Self {
+        self.with(damage_type, 1.)
+    }
+
+    /// scales the damage by the modifier for the damage type
+    pub fn apply(&self, damage_type: DamageType, damage: i32) -> i32 {
+        let modifier = *self.modifiers.get(&damage_type).unwrap_or(&0.);
+        ((damage as f32) * (1. - modifier).max(0.)).round() as i32
+    }
+}
+
+/// Implemented by anything that can attack, usually a component
+pub trait AttackStats {
+    /// the inclusive range of the main attack's damage
+    fn damage_range(&self) -> (i32, i32);
+
+    /// the type of damage dealt by the main attack
+    fn damage_type(&self) -> DamageType {
+        DamageType::Pure
+    }
+
+    fn crit_chance(&self) -> f32 {
+        0.
+    }
+
+    /// extra damage added on top of the main attack, which is not affected by crits
+    fn bonus_damage(&self) -> Vec<(DamageType, i32)> {
+        Vec::default()
+    }
+}
+
+/// Implemented by anything that can be attacked, usually a component
+pub trait DefenceStats {
+    /// the armour which reduces the given type of damage
+    fn armour_for(&self, damage_type: DamageType) -> i32;
+
+    fn resistances(&self) -> Option<&Resistances> {
+        None
+    }
+
+    /// the chance to avoid an attack entirely
+    fn dodge_chance(&self) -> f32 {
+        0.
+    }
+
+    /// the chance to reduce an attack by the calculator's `block_reduction`
+    fn block_chance(&self) -> f32 {
+        0.
+    }
+}
+
+/// Reduces the damage of an attack by armour
+pub trait DamageModel: Send + Sync {
+    fn mitigate(&self, attack: i32, armour: i32) -> i32;
+}
+
+/// `a² / (a + d)`, armour is very effective against weak attacks and less so against strong ones
+#[derive(Default)]
+pub struct QuadraticArmour;
+
+impl DamageModel for QuadraticArmour {
+    fn mitigate(&self, attack: i32, armour: i32) -> i32 {
+        if attack <= 0 {
+            return 0;
+        }
+
+        attack * attack / (attack + armour)
+    }
+}
+
+/// Armour is subtracted from the attack, down to a minimum damage
+#[derive(Default)]
+pub struct FlatArmour {
+    pub minimum_damage: i32,
+}
+
+impl DamageModel for FlatArmour {
+    fn mitigate(&self, attack: i32, armour: i32) -> i32 {
+        if attack <= 0 {
+            return 0;
+        }
+
+        (attack - armour).max(self.minimum_damage)
+    }
+}
+
+/// Each point of armour reduces damage by one percent, up to a maximum reduction
+pub struct PercentageArmour {
+    /// the largest fraction of damage armour can prevent, i.e. 0.75
+    pub max_reduction: f32,
+}
+
+impl Default for PercentageArmour {
+    fn default() -> Self {
+        PercentageArmour {
+            max_reduction: 0.75,
+        }
+    }
+}
+
+impl DamageModel for PercentageArmour {
+    fn mitigate(&self, attack: i32, armour: i32) -> i32 {
+        if attack <= 0 {
+            return 0;
+        }
+
+        let reduction = (armour as f32 / 100.).max(0.).min(self.max_reduction);
+        ((attack as f32) * (1. - reduction)).round() as i32
+    }
+}
+
+/// The random parts of an attack, rolled before the damage is calculated
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CombatRolls {
+    pub damage: i32,
+    pub is_crit: bool,
+    pub is_dodged: bool,
+    pub is_blocked: bool,
+}
+
+/// The outcome of an attack, broken down into each step of the calculation
+#[derive(Clone, Debug, Default)]
+pub struct CombatResult {
+    /// the total damage to apply
+    pub damage: i32,
+
+    /// the damage dealt by each type, after armour and resistances but before blocking
+    pub damage_by_type: Vec<(DamageType, i32)>,
+
+    /// the damage rolled, including crits and bonus damage
+    pub raw_damage: i32,
+
+    /// the damage prevented by armour
+    pub mitigated: i32,
+
+    /// the damage prevented by resistances, negative if weaknesses added damage
+    pub resisted: i32,
+
+    /// the damage prevented by a block
+    pub blocked: i32,
+
+    pub is_crit: bool,
+    pub is_dodged: bool,
+    pub is_blocked: bool,
+
+    // status effects
+    pub burning: bool,
+    pub poisoned: bool,
+    pub frozen: bool,
+    pub shocked: bool,
+}
+
+/// A resource which resolves attacks using the chosen `DamageModel`
+pub struct CombatCalculator {
+    pub model: Box<dyn DamageModel>,
+    pub crit_multiplier: f32,
+
+    /// the fraction of damage prevented by a successful block
+    pub block_reduction: f32,
+}
+
+impl Default for CombatCalculator {
+    fn default() -> Self {
+        CombatCalculator {
+            model: Box::new(QuadraticArmour),
+            crit_multiplier: 2.,
+            block_reduction: 0.5,
+        }
+    }
+}
+
+impl CombatCalculator {
+    pub fn new(model: Box<dyn DamageModel>) -> Self {
+        CombatCalculator {
+            model,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_crit_multiplier(mut self, crit_multiplier: f32) -> Self {
+        self.crit_multiplier = crit_multiplier;
+        self
+    }
+
+    pub fn with_block_reduction(mut self, block_reduction: f32) -> Self {
+        self.block_reduction = block_reduction;
+        self
+    }
+
+    /// Rolls the random parts of an attack
+    pub fn roll(&self, attack: &dyn AttackStats, defence: &dyn DefenceStats) -> CombatRolls {
+        let (min_damage, max_damage) = attack.damage_range();
+
+        CombatRolls {
+            damage: RNG::i32_between(min_damage, max_damage),
+            is_crit: RNG::test(attack.crit_chance()),
+            is_dodged: RNG::test(defence.dodge_chance()),
+            is_blocked: RNG::test(defence.block_chance()),
+        }
+    }
+
+    /// Resolves a combat between the given attacker and defender, returning a combat result
+    pub fn resolve(&self, attack: &dyn AttackStats, defence: &dyn DefenceStats) -> CombatResult {
+        self.resolve_rolls(attack, defence, self.roll(attack, defence))
+    }
+
+    /// Resolves a combat with pre-rolled random values
+    pub fn resolve_rolls(
+        &self,
+        attack: &dyn AttackStats,
+        defence: &dyn DefenceStats,
+        rolls: CombatRolls,
+    ) -> CombatResult {
+        let mut result = CombatResult {
+            is_crit: rolls.is_crit,
+            is_dodged: rolls.is_dodged,
+            ..Default::default()
+        };
+
+        if rolls.is_dodged {
+            return result;
+        }
+
+        let mut main_damage = rolls.damage;
+        if rolls.is_crit {
+            main_damage = (main_damage as f32 * self.crit_multiplier).round() as i32;
+        }
+
+        let mut damages = vec![(attack.damage_type(), main_damage)];
+        damages.extend(attack.bonus_damage());
+
+        for (damage_type, raw) in damages.into_iter() {
+            if raw <= 0 {
+                continue;
+            }
+
+            let mitigated = self.model.mitigate(raw, defence.armour_for(damage_type));
+            let damage = match defence.resistances() {
+                None => mitigated,
+                Some(resistances) => resistances.apply(damage_type, mitigated),
+            };
+
+            result.raw_damage += raw;
+            result.mitigated += raw - mitigated;
+            result.resisted += mitigated - damage;
+            result.damage += damage;
+            result.damage_by_type.push((damage_type, damage));
+
+            if damage <= 0 {
+                continue;
+            }
+
+            match damage_type {
+                DamageType::Pure => {}
+                DamageType::Fire => result.burning = true,
+                DamageType::Shock => result.shocked = true,
+                DamageType::Poison => result.poisoned = true,
+                DamageType::Ice => result.frozen = true,
+            };
+        }
+
+        if rolls.is_blocked && result.damage > 0 {
+            result.is_blocked = true;
+            result.blocked = (result.damage as f32 * self.block_reduction).round() as i32;
+            result.damage -= result.blocked;
+        }
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestAttack(DamageType, Vec<(DamageType, i32)>);
+
+    impl AttackStats for TestAttack {
+        fn damage_range(&self) -> (i32, i32) {
+            (10, 10)
+        }
+
+        fn damage_type(&self) -> DamageType {
+            self.0
+        }
+
+        fn bonus_damage(&self) -> Vec<(DamageType, i32)> {
+            self.1.clone()
+        }
+    }
+
+    struct TestDefence(i32, Resistances);
+
+    impl DefenceStats for TestDefence {
+        fn armour_for(&self, damage_type: DamageType) -> i32 {
+            match damage_type {
+                DamageType::Pure => self.0,
+                _ => 0,
+            }
+        }
+
+        fn resistances(&self) -> Option<&Resistances> {
+            Some(&self.1)
+        }
+    }
+
+    fn rolls() -> CombatRolls {
+        CombatRolls {
+            damage: 10,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn damage_models() {
+        assert_eq!(QuadraticArmour.mitigate(10, 10), 5);
+        assert_eq!(QuadraticArmour.mitigate(0, 0), 0);
+        assert_eq!(FlatArmour { minimum_damage: 1 }.mitigate(10, 4), 6);
+        assert_eq!(FlatArmour { minimum_damage: 1 }.mitigate(10, 40), 1);
+        assert_eq!(PercentageArmour::default().mitigate(10, 20), 8);
+        assert_eq!(PercentageArmour::default().mitigate(100, 200), 25);
+    }
+
+    #[test]
+    fn reports_each_component() {
+        let calculator = CombatCalculator::default();
+        let attack = TestAttack(DamageType::Pure, vec![(DamageType::Fire, 4)]);
+        let defence = TestDefence(10, Resistances::default().with(DamageType::Fire, 0.5));
+
+        let result = calculator.resolve_rolls(&attack, &defence, rolls());
+        assert_eq!(result.raw_damage, 14);
+        assert_eq!(result.mitigated, 5);
+        assert_eq!(result.resisted, 2);
+        assert_eq!(result.damage, 7);
+        assert_eq!(
+            result.damage_by_type,
+            vec![(DamageType::Pure, 5), (DamageType::Fire, 2)]
+        );
+        assert!(result.burning);
+    }
+
+    #[test]
+    fn applies_resistances_weaknesses_and_immunities() {
+        let calculator = CombatCalculator::default();
+        let defence = TestDefence(
+            0,
+            Resistances::default()
+                .with(DamageType::Fire, -0.5)
+                .with(DamageType::Ice, 0.5)
+                .immune_to(DamageType::Poison),
+        );
+
+        let weak =
+            calculator.resolve_rolls(&TestAttack(DamageType::Fire, vec![]), &defence, rolls());
+        assert_eq!(weak.damage, 15);
+        assert_eq!(weak.resisted, -5);
+
+        let resisted =
+            calculator.resolve_rolls(&TestAttack(DamageType::Ice, vec![]), &defence, rolls());
+        assert_eq!(resisted.damage, 5);
+        assert_eq!(resisted.resisted, 5);
+
+        let immune =
+            calculator.resolve_rolls(&TestAttack(DamageType::Poison, vec![]), &defence, rolls());
+        assert_eq!(immune.damage, 0);
+        assert_eq!(immune.resisted, 10);
+        assert!(!immune.poisoned);
+    }
+
+    #[test]
+    fn crits_blocks_and_dodges() {
+        let calculator = CombatCalculator::new(Box::new(FlatArmour::default()))
+            .with_crit_multiplier(3.)
+            .with_block_reduction(0.5);
+        let attack = TestAttack(DamageType::Pure, Vec::default());
+        let defence = TestDefence(0, Resistances::default());
+
+        let crit = calculator.resolve_rolls(
+            &attack,
+            &defence,
+            CombatRolls {
+                is_crit: true,
+                is_blocked: true,
+                ..rolls()
+            },
+        );
+        assert_eq!(crit.raw_damage, 30);
+        assert_eq!(crit.blocked, 15);
+        assert_eq!(crit.damage, 15);
+
+        let dodged = calculator.resolve_rolls(
+            &attack,
+            &defence,
+            CombatRolls {
+                is_dodged: true,
+                ..rolls()
+            },
+        );
+        assert!(dodged.is_dodged);
+        assert_eq!(dodged.damage, 0);
+    }
+}
@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 
+pub mod damage;
+pub mod threat;
+
 pub mod prelude {
+    pub use crate::damage::*;
+    pub use crate::threat::*;
     pub use crate::*;
 }
 
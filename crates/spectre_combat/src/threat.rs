@@ -0,0 +1,175 @@
+/// Sources are forgotten once their threat drops below this much for each second of the
+/// frame. Scaling it by the frame time means threat which is added a little every frame can
+/// still build up at high frame rates
+const FORGET_THREAT_PER_SECOND: f32 = 0.01;
+
+/// Tracks how much threat each source has generated against a unit. Threat decays over time,
+/// and the unit only switches targets when another source beats its current target by a margin
+#[derive(Clone, Debug)]
+pub struct ThreatTable<T> {
+    pub entries: Vec<(T, f32)>,
+
+    /// the source currently being targeted
+    pub current: Option<T>,
+
+    /// the fraction of threat lost each second
+    pub decay_rate: f32,
+
+    /// another source needs this many times the current target's threat to take over, i.e. 1.1
+    pub switch_margin: f32,
+}
+
+impl<T> Default for ThreatTable<T> {
+    fn default() -> Self {
+        ThreatTable {
+            entries: Vec::default(),
+            current: None,
+            decay_rate: 0.1,
+            switch_margin: 1.1,
+        }
+    }
+}
+
+impl<T: Copy + PartialEq> ThreatTable<T> {
+    pub fn new(decay_rate: f32, switch_margin: f32) -> Self {
+        ThreatTable {
+            decay_rate,
+            switch_margin,
+            ..Default::default()
+        }
+    }
+
+    pub fn add(&mut self, source: T, amount: f32) {
+        match self.entries.iter_mut().find(|(s, _)| *s == source) {
+            Some((_, threat)) => *threat += amount,
+            None => self.entries.push((source, amount)),
+        };
+    }
+
+    pub fn threat(&self, source: T) -> f32 {
+        self.entries
+            .iter()
+            .find(|(s, _)| *s == source)
+            .map(|(_, threat)| *threat)
+            .unwrap_or(0.)
+    }
+
+    /// removes a source, i.e. when it can no longer be attacked
+    pub fn remove(&mut self, source: T) {
+        self.entries.retain(|(s, _)| *s != source);
+        if self.current == Some(source) {
+            self.current = None;
+        }
+    }
+
+    /// keeps only the sources which match the predicate
+    pub fn retain<F: Fn(&T) -> bool>(&mut self, keep: F) {
+        let removed: Vec<T> = self
+            .entries
+            .iter()
+            .filter(|(s, _)| !keep(s))
+            .map(|(s, _)| *s)
+            .collect();
+
+        for source in removed.into_iter() {
+            self.remove(source);
+        }
+    }
+
+    /// reduces all threat by the decay rate, forgetting sources with no threat left
+    pub fn decay(&mut self, delta: f32) {
+        let factor = (1. - self.decay_rate * delta).max(0.);
+        for (_, threat) in self.entries.iter_mut() {
+            *threat *= factor;
+        }
+
+        let forgotten: Vec<T> = self
+            .entries
+            .iter()
+            .filter(|(_, threat)| *threat < FORGET_THREAT_PER_SECOND * delta)
+            .map(|(s, _)| *s)
+            .collect();
+
+        for source in forgotten.into_iter() {
+            self.remove(source);
+        }
+    }
+
+    /// the source with the most threat
+    pub fn highest(&self) -> Option<(T, f32)> {
+        self.entries
+            .iter()
+            .fold(None, |best, &(source, threat)| match best {
+                Some((_, best_threat)) if best_threat >= threat => best,
+                _ => Some((source, threat)),
+            })
+    }
+
+    /// switches to the highest threat source if it beats the current target by the margin,
+    /// returning the new target
+    pub fn update_target(&mut self) -> Option<T> {
+        let (highest, highest_threat) = match self.highest() {
+            None => {
+                self.current = None;
+                return None;
+            }
+            Some(best) => best,
+        };
+
+        self.current = match self.current {
+            Some(current) if highest_threat <= self.threat(current) * self.switch_margin => {
+                Some(current)
+            }
+            _ => Some(highest),
+        };
+
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_switches_targets_past_the_margin() {
+        let mut table = ThreatTable::new(0., 1.5);
+        table.add(1, 10.);
+        assert_eq!(table.update_target(), Some(1));
+
+        table.add(2, 14.);
+        assert_eq!(table.update_target(), Some(1));
+
+        table.add(2, 2.);
+        assert_eq!(table.update_target(), Some(2));
+
+        table.remove(2);
+        assert_eq!(table.current, None);
+        assert_eq!(table.update_target(), Some(1));
+    }
+
+    #[test]
+    fn threat_decays_over_time() {
+        let mut table = ThreatTable::new(0.5, 1.1);
+        table.add("hero", 10.);
+        table.decay(1.);
+        assert_eq!(table.threat("hero"), 5.);
+
+        table.decay(2.);
+        assert!(table.entries.is_empty());
+        assert_eq!(table.update_target(), None);
+    }
+
+    #[test]
+    fn builds_up_small_amounts_at_high_frame_rates() {
+        let mut table = ThreatTable::new(0.1, 1.1);
+        let delta = 0.001;
+        for _ in 0..1000 {
+            table.add("hero", 2. * delta);
+            table.decay(delta);
+        }
+
+        // a second of 2 threat per second, less what decayed away
+        assert!((table.threat("hero") - 1.9).abs() < 0.05);
+    }
+}
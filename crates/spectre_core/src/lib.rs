@@ -46,6 +46,12 @@ impl BuffableStatistic {
         self.recalculate();
     }
 
+    /// adds a buff and updates the value
+    pub fn add_buff(&mut self, buff: Buff) {
+        self.buffs.push(buff);
+        self.recalculate();
+    }
+
     /// updates a buffable statistic as effects expire
     pub fn update(&mut self, game_time: f32) -> bool {
         // remove old buffs
@@ -56,7 +62,7 @@ impl BuffableStatistic {
 
         {
             self.buffs
-                .retain(|buff| (buff.expiry - 0.).abs() < 0.05 || buff.expiry < game_time);
+                .retain(|buff| (buff.expiry - 0.).abs() < 0.05 || buff.expiry > game_time);
         }
 
         if self.buffs.len() != len {
@@ -221,3 +227,33 @@ fn mana_regeneration(time: Res<GameTime>, mut mana: Mut<Mana>) {
         mana.current_mana = 0.;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removes_expired_buffs() {
+        let mut stat = BuffableStatistic::new(10.);
+        stat.add_buff(Buff {
+            expiry: 5.,
+            percentage: 0.,
+            amount: 2.,
+        });
+        stat.add_buff(Buff {
+            expiry: 0.,
+            percentage: 0.5,
+            amount: 0.,
+        });
+        assert_eq!(stat.value, 17.);
+
+        // the timed buff is still active
+        assert!(!stat.update(4.));
+        assert_eq!(stat.value, 17.);
+
+        // only the timed buff expires, buffs with no expiry are permanent
+        assert!(stat.update(6.));
+        assert_eq!(stat.buffs.len(), 1);
+        assert_eq!(stat.value, 15.);
+    }
+}
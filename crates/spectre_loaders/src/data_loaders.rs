@@ -1,7 +1,9 @@
-use bevy::asset::AssetLoader;
+use bevy::{asset::AssetLoader, ecs::Resource, prelude::*};
 use ron::de::from_bytes;
 use serde::de::Deserialize;
-use std::path::Path;
+use std::{marker::PhantomData, path::Path};
+
+use crate::LoadingStatus;
 
 /// A generic data file loader which loads RON files from the assets folder
 /// and deserializes them into the provided type. There should be a 1:1 mapping
@@ -31,3 +33,184 @@ where
         self.matching_extensions.as_slice()
     }
 }
+
+/// A generic loader for JSON files, i.e. sidecar files exported by other tools.
+/// Works the same way as the `DataFileLoader`, with a 1:1 mapping between extensions and asset types
+#[derive(Default)]
+pub struct JsonFileLoader {
+    matching_extensions: Vec<&'static str>,
+}
+
+impl JsonFileLoader {
+    pub fn from_extensions(matching_extensions: Vec<&'static str>) -> Self {
+        JsonFileLoader {
+            matching_extensions,
+        }
+    }
+}
+
+impl<TAsset> AssetLoader<TAsset> for JsonFileLoader
+where
+    for<'de> TAsset: Deserialize<'de>,
+{
+    fn from_bytes(&self, _asset_path: &Path, bytes: Vec<u8>) -> Result<TAsset, anyhow::Error> {
+        Ok(serde_json::from_slice::<TAsset>(bytes.as_slice())?)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        self.matching_extensions.as_slice()
+    }
+}
+
+/// Sent whenever a data resource is replaced by newly loaded (or hot reloaded) assets
+pub struct DataReloaded<TData: Resource> {
+    pub handles: Vec<Handle<TData>>,
+}
+
+/// Implemented by data resources which can be split across multiple files.
+/// Files are merged in the order they are given to the `DataResourcePlugin`,
+/// by default a later file replaces everything loaded before it.
+pub trait MergeData: Sized + Clone {
+    fn merge(&mut self, other: &Self) {
+        *self = other.clone();
+    }
+}
+
+/// Reads the data files straight from disk and merges them in order, the same way the
+/// `DataResourcePlugin` does. Panics if a file can't be read or parsed, so it's only built
+/// for tests and tools (with the "tools" feature) rather than the game
+#[cfg(any(test, feature = "tools"))]
+pub fn load_merged<TData>(paths: &[&str]) -> TData
+where
+    TData: MergeData + Default + serde::de::DeserializeOwned,
+{
+    let mut merged = TData::default();
+    for path in paths.iter() {
+        let contents = std::fs::read_to_string(path)
+            .unwrap_or_else(|error| panic!("Couldn't read {}: {}", path, error));
+        let loaded: TData = ron::de::from_str(&contents)
+            .unwrap_or_else(|error| panic!("Couldn't parse {}: {}", path, error));
+        merged.merge(&loaded);
+    }
+
+    merged
+}
+
+/// Tracks the data assets that are merged and mirrored into the `TData` resource
+pub struct DataResourceWatcher<TData: Resource> {
+    /// The paths to load the data files from
+    pub paths: Vec<&'static str>,
+
+    /// The handles of the data assets, empty until loading has been requested
+    handles: Vec<Handle<TData>>,
+
+    /// Set to true once every file has been merged into the resource for the first time
+    is_loaded: bool,
+
+    asset_event_reader: EventReader<AssetEvent<TData>>,
+}
+
+/// Loads one or more RON data files through the `DataFileLoader` and mirrors them into a
+/// resource of the same type. Whenever any of the files are reloaded the resource is rebuilt
+/// and a `DataReloaded<TData>` event is sent, so enable `AssetServer::watch_for_changes` to
+/// hot reload data while playing.
+pub struct DataResourcePlugin<TData> {
+    paths: Vec<&'static str>,
+    extensions: Vec<&'static str>,
+    marker: PhantomData<TData>,
+}
+
+impl<TData> DataResourcePlugin<TData> {
+    pub fn new(paths: Vec<&'static str>, extensions: Vec<&'static str>) -> Self {
+        DataResourcePlugin {
+            paths,
+            extensions,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<TData> Plugin for DataResourcePlugin<TData>
+where
+    TData: Resource + MergeData + Default,
+    for<'de> TData: Deserialize<'de>,
+{
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_asset::<TData>()
+            .add_asset_loader_from_instance::<TData, DataFileLoader>(
+                DataFileLoader::from_extensions(self.extensions.clone()),
+            )
+            .init_resource::<TData>()
+            .add_event::<DataReloaded<TData>>()
+            .add_resource(DataResourceWatcher::<TData> {
+                paths: self.paths.clone(),
+                handles: Vec::default(),
+                is_loaded: false,
+                asset_event_reader: EventReader::default(),
+            })
+            .add_system(data_resource_system::<TData>.system());
+    }
+}
+
+/// Requests the data files, then rebuilds the resource each time one of them is created or modified
+fn data_resource_system<TData>(
+    asset_server: Res<AssetServer>,
+    data_assets: Res<Assets<TData>>,
+    asset_events: Res<Events<AssetEvent<TData>>>,
+    mut loading_status: ResMut<LoadingStatus>,
+    mut watcher: ResMut<DataResourceWatcher<TData>>,
+    mut reloaded_events: ResMut<Events<DataReloaded<TData>>>,
+    mut data: ResMut<TData>,
+) where
+    TData: Resource + MergeData + Default,
+{
+    if watcher.handles.is_empty() {
+        watcher.handles = watcher
+            .paths
+            .iter()
+            .map(|path| asset_server.load::<TData, _>(path).unwrap())
+            .collect();
+        loading_status.items_to_load += watcher.handles.len();
+        return;
+    }
+
+    let handles = watcher.handles.clone();
+    let mut changed = false;
+    for event in watcher.asset_event_reader.iter(&asset_events) {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                if handles.contains(handle) {
+                    changed = true;
+                }
+            }
+            AssetEvent::Removed { .. } => {}
+        }
+    }
+
+    if !changed {
+        return;
+    }
+
+    // wait until every file is available, then merge them in order. Every file goes through
+    // `merge`, including the first, so resources which check their data while merging see it all
+    let mut merged = TData::default();
+    for handle in handles.iter() {
+        let loaded = match data_assets.get(handle) {
+            None => return,
+            Some(loaded) => loaded,
+        };
+
+        merged.merge(loaded);
+    }
+
+    *data = merged;
+
+    if !watcher.is_loaded {
+        watcher.is_loaded = true;
+        loading_status.items_loaded += handles.len();
+    } else {
+        println!("Reloaded data files {:?}", watcher.paths);
+    }
+
+    reloaded_events.send(DataReloaded { handles });
+}
@@ -1,4 +1,4 @@
-use rand::Rng;
+use rand::{rngs::StdRng, Rng, SeedableRng};
 /// A quasi-random number generator (i.e. DOTA 2 style proc)
 pub struct QRNG {
     pub initial: f32,
@@ -60,4 +60,63 @@ impl RNG {
     pub fn f32_between(min: f32, max: f32) -> f32 {
         rand::thread_rng().gen_range(min, max)
     }
+
+    /// a random seed for a `SeededRNG`
+    pub fn seed() -> u64 {
+        rand::thread_rng().gen()
+    }
+}
+
+/// A random number generator which gives the same sequence for the same seed,
+/// i.e. for procedurally generated content that can be replayed
+pub struct SeededRNG {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl SeededRNG {
+    pub fn new(seed: u64) -> Self {
+        SeededRNG {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn next(&mut self) -> f32 {
+        self.rng.gen()
+    }
+
+    pub fn test(&mut self, p: f32) -> bool {
+        self.rng.gen_bool(p as f64)
+    }
+
+    /// Inclusive of the last element
+    pub fn i32_between(&mut self, min: i32, max: i32) -> i32 {
+        self.rng.gen_range(min, max + 1)
+    }
+
+    /// Exclusive of the last element
+    pub fn usize_between(&mut self, min: usize, max: usize) -> usize {
+        self.rng.gen_range(min, max)
+    }
+
+    /// exclusive of the last element
+    pub fn f32_between(&mut self, min: f32, max: f32) -> f32 {
+        self.rng.gen_range(min, max)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seeded_rng_repeats() {
+        let mut first = SeededRNG::new(47);
+        let mut second = SeededRNG::new(47);
+
+        for _ in 0..10 {
+            assert_eq!(first.usize_between(0, 100), second.usize_between(0, 100));
+        }
+    }
 }
@@ -1,20 +1,28 @@
-/// Loads in an ability database. This could better be done from a file
-/// but its a bit tricky to work out the RON format for these so I'll start here.
+/// Loads in an ability database from the RON files in `assets/data`, one file per class.
+/// The files are merged into a single database when they are loaded.
 ///
 /// The following conventions are used for ability IDs:
 ///
-/// - 0-999 are general abilities
-/// - 1000 - 1999 are healer abilities
-/// - 2000 - 2999 are rogue abilities
-/// - 3000 - 3999 are warrior abilities
-use crate::constants::*;
-
+/// - 0-999 are general abilities (general.abr)
+/// - 1000 - 1999 are mage abilities (mage.abr)
+/// - 2000 - 2999 are healer abilities (healer.abr)
+/// - 3000 - 3999 are warrior abilities (warrior.abr)
+/// - 9000 - 9999 are boss abilities (bosses.abr), which heroes can't purchase
 use super::*;
-use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
+use spectre_loaders::data_loaders::MergeData;
 use std::collections::HashMap;
 
-#[derive(Deserialize, Serialize)]
+/// The ability files to load, merged in this order
+pub const ABILITY_DATA_FILES: [&str; 5] = [
+    "assets/data/general.abr",
+    "assets/data/mage.abr",
+    "assets/data/healer.abr",
+    "assets/data/warrior.abr",
+    "assets/data/bosses.abr",
+];
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 pub struct AbilityDatabase {
     pub abilities: HashMap<u16, AbilityDefinition>,
 }
@@ -24,8 +32,29 @@ impl AbilityDatabase {
     pub fn get(&mut self, id: u16) -> &mut AbilityDefinition {
         self.abilities.get_mut(&id).unwrap()
     }
+}
+
+impl MergeData for AbilityDatabase {
+    fn merge(&mut self, other: &Self) {
+        for (id, ability) in other.abilities.iter() {
+            if self.abilities.contains_key(id) {
+                println!("Duplicate ability id {} found, replacing", id);
+            }
+
+            self.abilities.insert(*id, ability.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectre_core::Buff;
+    use spectre_loaders::data_loaders::load_merged;
 
-    pub fn new() -> Self {
+    /// The hand written table the ability files were converted from, to check nothing was
+    /// lost in the move. Keep it in step with any deliberate changes to the files
+    fn built_in_abilities() -> HashMap<u16, AbilityDefinition> {
         let mut abilities: HashMap<u16, AbilityDefinition> = HashMap::new();
 
         // level 2 upgrade
@@ -49,6 +78,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 50.,
                         },
+                        duration: None,
                     }),
                     AbilityDetail::Buff(AbilityBuffDetail {
                         buff_type: BuffType::Mana,
@@ -57,6 +87,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 50.,
                         },
+                        duration: None,
                     }),
                 ],
             },
@@ -83,6 +114,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 50.,
                         },
+                        duration: None,
                     }),
                     AbilityDetail::Buff(AbilityBuffDetail {
                         buff_type: BuffType::MovementSpeed,
@@ -91,6 +123,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 25.,
                         },
+                        duration: None,
                     }),
                 ],
             },
@@ -117,6 +150,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 50.,
                         },
+                        duration: None,
                     }),
                     AbilityDetail::Buff(AbilityBuffDetail {
                         buff_type: BuffType::Regeneration,
@@ -125,6 +159,7 @@ impl AbilityDatabase {
                             percentage: 0.,
                             amount: 2.,
                         },
+                        duration: None,
                     }),
                 ],
             },
@@ -152,7 +187,7 @@ impl AbilityDatabase {
                         },
                         96,
                     ),
-                    AbilityDetail::SpawnAnimation(FLAME_WALL_ID, 0, 9),
+                    AbilityDetail::SpawnEffect("flame_wall".to_string()),
                 ],
             },
         );
@@ -193,7 +228,7 @@ impl AbilityDatabase {
                 mana_cost: 30.,
                 effects: vec![
                     AbilityDetail::Heal(AbilityHealDetail { burst_heal: 50. }),
-                    AbilityDetail::SpawnAnimation(HEAL_ID, 0, 7),
+                    AbilityDetail::SpawnEffect("heal".to_string()),
                 ],
             },
         );
@@ -213,7 +248,7 @@ impl AbilityDatabase {
                 mana_cost: 60.,
                 effects: vec![
                     AbilityDetail::Revive(AbilityReviveDetail { revive_time: 0. }),
-                    AbilityDetail::SpawnAnimation(HEAL_ID, 0, 7),
+                    AbilityDetail::SpawnEffect("heal".to_string()),
                 ],
             },
         );
@@ -226,16 +261,19 @@ impl AbilityDatabase {
                 xp_cost: 200,
                 prerequisites: vec![],
                 name: "Smash".to_string(),
-                description: "Hit a single enemy, very hard".to_string(),
+                description: "Hit a single enemy, very hard, stunning it".to_string(),
                 passive: false,
                 slot_number: 1,
                 cooldown: 15.,
                 mana_cost: 20.,
-                effects: vec![AbilityDetail::Attack(AbilityAttackDetail {
-                    damage_type: DamageType::Pure,
-                    min_damage: 15,
-                    max_damage: 75,
-                })],
+                effects: vec![
+                    AbilityDetail::Attack(AbilityAttackDetail {
+                        damage_type: DamageType::Pure,
+                        min_damage: 15,
+                        max_damage: 75,
+                    }),
+                    AbilityDetail::Stun(1.),
+                ],
             },
         );
 
@@ -247,29 +285,71 @@ impl AbilityDatabase {
                 xp_cost: 200,
                 prerequisites: vec![],
                 name: "Shockwave".to_string(),
-                description: "Damage nearby enemies".to_string(),
+                description: "Damage nearby enemies and draw their attacks".to_string(),
                 passive: false,
                 slot_number: 2,
                 cooldown: 20.,
                 mana_cost: 20.,
-                effects: vec![AbilityDetail::AttackArea(
-                    AbilityAttackDetail {
-                        damage_type: DamageType::Pure,
-                        min_damage: 20,
-                        max_damage: 50,
-                    },
-                    50,
-                )],
+                effects: vec![
+                    AbilityDetail::AttackArea(
+                        AbilityAttackDetail {
+                            damage_type: DamageType::Pure,
+                            min_damage: 20,
+                            max_damage: 50,
+                        },
+                        50,
+                    ),
+                    AbilityDetail::Taunt(50.),
+                ],
             },
         );
 
-        AbilityDatabase { abilities }
+        abilities
     }
-}
 
-/// Loads the abilities into the ability database
-impl FromResources for AbilityDatabase {
-    fn from_resources(_: &Resources) -> Self {
-        AbilityDatabase::new()
+    #[test]
+    fn ability_files_match_the_built_in_table() {
+        let database = load_merged::<AbilityDatabase>(&ABILITY_DATA_FILES);
+        for (id, expected) in built_in_abilities().iter() {
+            let loaded = match database.abilities.get(id) {
+                None => panic!("Ability {} is missing from the ability files", id),
+                Some(loaded) => loaded,
+            };
+
+            // compare every field through their RON form, the ability types don't implement PartialEq
+            assert_eq!(
+                ron::ser::to_string(loaded).unwrap(),
+                ron::ser::to_string(expected).unwrap(),
+                "Ability {} doesn't match the built in table",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn loads_all_ability_files() {
+        let mut database = load_merged::<AbilityDatabase>(&ABILITY_DATA_FILES);
+
+        let mut ids = database.abilities.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![0, 1, 2, 1000, 1001, 2000, 2001, 3000, 3001, 9000, 9001, 9002]
+        );
+
+        // spot check the values carried over from the old hand written database
+        let flame_wall = database.get(1000);
+        assert_eq!(flame_wall.name, "Flame Wall");
+        assert_eq!(flame_wall.xp_cost, 300);
+        assert_eq!(flame_wall.slot_number, 1);
+        assert_eq!(flame_wall.effects.len(), 2);
+
+        let level_3 = database.get(1);
+        assert_eq!(level_3.prerequisites, vec![0]);
+        assert!(level_3.passive);
+
+        let revive = database.get(2001);
+        assert_eq!(revive.mana_cost, 60.);
+        assert_eq!(revive.cooldown, 20.);
     }
 }
@@ -1,6 +1,8 @@
 use serde::{Deserialize, Serialize};
 use spectre_core::Buff;
 
+pub use spectre_combat::damage::DamageType;
+
 pub mod ability_data;
 pub mod systems;
 
@@ -15,7 +17,7 @@ pub mod systems;
 /// - Applying the effects of those abliities as part of the combat system
 ///
 /// TODO:
-///   - side effects like stun, damage over time
+///   - side effects like damage over time
 
 #[derive(Clone, Deserialize, Serialize)]
 pub enum BuffType {
@@ -26,15 +28,6 @@ pub enum BuffType {
     MovementSpeed,
 }
 
-#[derive(Clone, Deserialize, Serialize)]
-pub enum DamageType {
-    Pure,
-    Posion,
-    Shock,
-    Ice,
-    Fire,
-}
-
 #[derive(Clone, Deserialize, Serialize)]
 pub enum AbilityDetail {
     Attack(AbilityAttackDetail),
@@ -43,7 +36,13 @@ pub enum AbilityDetail {
     Buff(AbilityBuffDetail),
     Heal(AbilityHealDetail),
     Revive(AbilityReviveDetail),
-    SpawnAnimation(u128, usize, usize),
+    /// adds threat to every enemy in the ability's lane, drawing their attacks
+    Taunt(f32),
+    /// stuns an enemy in melee range for this many seconds, interrupting its swing.
+    /// Bosses stun every hero in their lane within their attack range instead
+    Stun(f32),
+    /// shows a named effect from the vfx database in the ability's lane
+    SpawnEffect(String),
 }
 
 #[derive(Clone, Deserialize, Serialize)]
@@ -57,6 +56,11 @@ pub struct AbilityAttackDetail {
 pub struct AbilityBuffDetail {
     pub buff_type: BuffType,
     pub buff: Buff,
+
+    /// how long the buff lasts once cast, in seconds. Used by bosses, as the expiry in the
+    /// data file can't know when the ability will be cast
+    #[serde(default)]
+    pub duration: Option<f32>,
 }
 
 #[derive(Clone, Deserialize, Serialize)]
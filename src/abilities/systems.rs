@@ -1,20 +1,27 @@
 use super::*;
-use crate::{components::*, events::RedrawAbilityUiEvent};
+use crate::{
+    components::*,
+    events::RedrawAbilityUiEvent,
+    hero_classes::{HeroClass, HeroClassDatabase},
+};
 use ability_data::AbilityDatabase;
 use bevy::prelude::*;
-use spectre_core::{Health, Mana, Movement};
+use spectre_core::{Buff, Health, Mana, Movement};
 use spectre_time::GameTime;
 
 pub fn ability_purchase_system(
     mut commands: Commands,
     game_time: Res<GameTime>,
     mut abilities: ResMut<AbilityDatabase>,
+    classes: Res<HeroClassDatabase>,
     mut player_score: ResMut<PlayerScore>,
     mut ability_redraw_event: ResMut<Events<RedrawAbilityUiEvent>>,
     mut purchase_requests: Query<(Entity, &AbilityPurchaseRequest)>,
     mut players: Query<(
         &mut Player,
+        &HeroClass,
         &mut PlayerAbilityActions,
+        &mut BaseAttack,
         &mut Defence,
         &mut Health,
         &mut Mana,
@@ -35,8 +42,16 @@ pub fn ability_purchase_system(
         }
 
         let mut found: bool = false;
-        for (mut player, mut actions, mut defence, mut health, mut mana, mut movement) in
-            &mut players.iter()
+        for (
+            mut player,
+            class,
+            mut actions,
+            mut attack,
+            mut defence,
+            mut health,
+            mut mana,
+            mut movement,
+        ) in &mut players.iter()
         {
             if player.player_id != request.player_id {
                 continue;
@@ -104,6 +119,39 @@ pub fn ability_purchase_system(
                 };
             }
 
+            // general abilities (0-999) are level ups, which also grow the hero's class stats
+            if ability.id < 1000 {
+                match classes.get(&class.id) {
+                    None => println!("Unknown hero class {}, skipping growth", class.id),
+                    Some(definition) => {
+                        let growth = &definition.growth;
+                        health.max_health.add_buff(Buff {
+                            expiry: 0.,
+                            percentage: 0.,
+                            amount: growth.health,
+                        });
+                        health.current_health = health.max_health.value;
+                        health.target_health = health.current_health;
+
+                        mana.max_mana.add_buff(Buff {
+                            expiry: 0.,
+                            percentage: 0.,
+                            amount: growth.mana,
+                        });
+                        mana.current_mana = mana.max_mana.value;
+
+                        defence.base_armour.add_buff(Buff {
+                            expiry: 0.,
+                            percentage: 0.,
+                            amount: growth.armour,
+                        });
+
+                        attack.min_attack_damage += growth.damage;
+                        attack.max_attack_damage += growth.damage;
+                    }
+                };
+            }
+
             // mark it as applied
             player_score.xp -= ability.xp_cost;
             found = true;
@@ -12,18 +12,10 @@ pub struct MaterialsAndTextures {
 
     pub nine_patch_texture: Handle<Texture>,
 
-    pub char1_atlas: Handle<TextureAtlas>,
-    pub char2_atlas: Handle<TextureAtlas>,
-    pub char3_atlas: Handle<TextureAtlas>,
-
     pub char1_portrait_material: Handle<ColorMaterial>,
     pub char2_portrait_material: Handle<ColorMaterial>,
     pub char3_portrait_material: Handle<ColorMaterial>,
 
-    pub wolf_atlas: Handle<TextureAtlas>,
-    pub bear_atlas: Handle<TextureAtlas>,
-    pub troll_atlas: Handle<TextureAtlas>,
-
     pub canyon_material: Handle<ColorMaterial>,
     pub boulder_material: Handle<ColorMaterial>,
     pub tree_material: Handle<ColorMaterial>,
@@ -49,53 +41,41 @@ pub struct MaterialsAndTextures {
     pub moving_audio: Handle<AudioSource>,
 }
 
+impl MaterialsAndTextures {
+    /// the portrait for a hero class's portrait key, i.e. "character1_portrait"
+    pub fn portrait(&self, key: &str) -> Handle<ColorMaterial> {
+        match key {
+            "character2_portrait" => self.char2_portrait_material,
+            "character3_portrait" => self.char3_portrait_material,
+            "character1_portrait" => self.char1_portrait_material,
+            _ => {
+                println!("Unknown portrait {}, using the first character", key);
+                self.char1_portrait_material
+            }
+        }
+    }
+
+    /// the sprite for a map decoration key, i.e. "tree"
+    pub fn decoration(&self, key: &str) -> Option<Handle<ColorMaterial>> {
+        match key {
+            "canyon" => Some(self.canyon_material),
+            "rock" => Some(self.boulder_material),
+            "tree" => Some(self.tree_material),
+            "obelisk" => Some(self.obelisk_material),
+            _ => None,
+        }
+    }
+}
+
 impl FromResources for MaterialsAndTextures {
     fn from_resources(resources: &Resources) -> Self {
         let mut materials = resources.get_mut::<Assets<ColorMaterial>>().unwrap();
-        let mut texture_atlases = resources.get_mut::<Assets<TextureAtlas>>().unwrap();
         let asset_server = resources.get_mut::<AssetServer>().unwrap();
 
-        // load sprite atlases for players
-        let handle_player: Handle<Texture> = Handle::from_u128(CHARACTER_1_SPRITE);
-        let texture_atlas_player =
-            TextureAtlas::from_grid(handle_player, Vec2::new(128., 32.), 4, 1);
-        let char1_atlas = texture_atlases.add(texture_atlas_player);
-
-        let handle_player2: Handle<Texture> = Handle::from_u128(CHARACTER_2_SPRITE);
-        let texture_atlas_player2 =
-            TextureAtlas::from_grid(handle_player2, Vec2::new(128., 32.), 4, 1);
-        let char2_atlas = texture_atlases.add(texture_atlas_player2);
-
-        let handle_player3: Handle<Texture> = Handle::from_u128(CHARACTER_3_SPRITE);
-        let texture_atlas_player3 =
-            TextureAtlas::from_grid(handle_player3, Vec2::new(128., 32.), 4, 1);
-        let char3_atlas = texture_atlases.add(texture_atlas_player3);
-
-        let handle_splatter: Handle<Texture> = Handle::from_u128(SPLATTER_ID);
-        let atlas_splatter =
-            TextureAtlas::from_grid(handle_splatter, Vec2::new(32. * 6., 32.), 4, 1);
-        let splatter_atlas = texture_atlases.add(atlas_splatter);
-
-        let handle_flame_wall: Handle<Texture> = Handle::from_u128(FLAME_WALL_ID);
-        let atlas_flame_wall =
-            TextureAtlas::from_grid(handle_flame_wall, Vec2::new(32. * 10., 96.), 10, 1);
-        let flame_wall_atlas = texture_atlases.add(atlas_flame_wall);
-
-        let handle_heal: Handle<Texture> = Handle::from_u128(HEAL_ID);
-        let atlas_heal = TextureAtlas::from_grid(handle_heal, Vec2::new(64. * 8., 64.), 8, 1);
-        let heal_atlas = texture_atlases.add(atlas_heal);
-
-        let handle_wolf: Handle<Texture> = Handle::from_u128(ENEMY_WOLF_SPRITE);
-        let atlas_wolf = TextureAtlas::from_grid(handle_wolf, Vec2::new(128., 32.), 4, 1);
-        let wolf_atlas = texture_atlases.add(atlas_wolf);
-
-        let handle_bear: Handle<Texture> = Handle::from_u128(ENEMY_BEAR_SPRITE);
-        let atlas_bear = TextureAtlas::from_grid(handle_bear, Vec2::new(128., 32.), 4, 1);
-        let bear_atlas = texture_atlases.add(atlas_bear);
-
-        let handle_troll: Handle<Texture> = Handle::from_u128(ENEMY_TROLL_SPRITE);
-        let atlas_troll = TextureAtlas::from_grid(handle_troll, Vec2::new(128., 32.), 4, 1);
-        let troll_atlas = texture_atlases.add(atlas_troll);
+        // atlases are built once the Aseprite sidecars are loaded, see `setup`
+        let splatter_atlas = Handle::from_u128(SPLATTER_ATLAS);
+        let flame_wall_atlas = Handle::from_u128(FLAME_WALL_ATLAS);
+        let heal_atlas = Handle::from_u128(HEAL_ATLAS);
 
         MaterialsAndTextures {
             ui_material: materials.add(Color::NONE.into()),
@@ -109,14 +89,6 @@ impl FromResources for MaterialsAndTextures {
 
             nine_patch_texture: Handle::from_u128(UI_CONTAINER_ID),
 
-            char1_atlas,
-            char2_atlas,
-            char3_atlas,
-
-            wolf_atlas,
-            bear_atlas,
-            troll_atlas,
-
             char1_portrait_material: materials.add(Handle::from_u128(CHARACTER_1_PORTRAIT).into()),
             char2_portrait_material: materials.add(Handle::from_u128(CHARACTER_2_PORTRAIT).into()),
             char3_portrait_material: materials.add(Handle::from_u128(CHARACTER_3_PORTRAIT).into()),
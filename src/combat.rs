@@ -1,175 +1,369 @@
 use crate::assets::MaterialsAndTextures;
 use bevy::prelude::*;
+use spectre_animations::prelude::{AnimationStateMachine, SpawnVfxEvent};
+use spectre_combat::{damage::CombatCalculator, threat::ThreatTable};
 use spectre_core::Health;
 use spectre_random::RNG;
 use spectre_time::GameTime;
 
+use crate::combat_log::{CombatEvent, CombatEventKind, Combatant};
 use crate::components::*;
 use crate::constants::*;
+use crate::floating_text::FloatingTextEvent;
+use crate::map::MapDefinition;
+use crate::projectiles::FireProjectileEvent;
 
 /// A combat system
 
-#[derive(Debug)]
-pub struct CombatResult {
-    pub damage: i32,
-    pub is_crit: bool,
+/// An enemy a hero could auto attack, with the details used by targeting policies
+#[derive(Clone, Copy, Debug)]
+pub struct TargetCandidate {
+    pub entity: Entity,
+    pub distance: f32,
+    pub health: f32,
+    pub damage_per_second: f32,
 
-    // status effects
-    pub burning: bool,
-    pub poisoned: bool,
-    pub frozen: bool,
-    pub shocked: bool,
-}
+    /// how much threat this enemy has built up against the hero choosing a target
+    pub threat: f32,
 
-fn calc_damage(attack: i32, defence: i32) -> i32 {
-    return attack * attack / (attack + defence);
+    /// the hero this enemy is attacking, if any
+    pub attacking: Option<Entity>,
+    pub attacking_obelisk: bool,
 }
 
-/// Resolves a combat between the given attacker and defender, returning a combat result
-pub fn resolve_combat(attack: &BaseAttack, defence: &Defence) -> CombatResult {
-    let is_crit = RNG::test(attack.crit_chance);
-
-    let mut base_attack = RNG::i32_between(attack.min_attack_damage, attack.max_attack_damage);
-    if is_crit {
-        base_attack *= 2;
-    }
-
-    let mut result = CombatResult {
-        is_crit,
-        damage: calc_damage(base_attack, defence.base_armour.value as i32),
-        frozen: false,
-        poisoned: false,
-        burning: false,
-        shocked: false,
+/// Picks a target from the candidates using the policy. Policies that look for a specific enemy
+/// fall back to the nearest if there isn't one
+pub fn select_target(
+    policy: TargetingPolicy,
+    hero: Entity,
+    candidates: &[TargetCandidate],
+) -> Option<Entity> {
+    let nearest = candidates
+        .iter()
+        .min_by(|a, b| a.distance.total_cmp(&b.distance));
+
+    let selected = match policy {
+        TargetingPolicy::Nearest => nearest,
+        TargetingPolicy::LowestHealth => candidates
+            .iter()
+            .min_by(|a, b| a.health.total_cmp(&b.health)),
+        // enemies which haven't built up any threat yet are ranked by how hard they hit
+        TargetingPolicy::HighestThreat => candidates.iter().max_by(|a, b| {
+            a.threat
+                .total_cmp(&b.threat)
+                .then(a.damage_per_second.total_cmp(&b.damage_per_second))
+        }),
+        TargetingPolicy::AttackingObelisk => candidates
+            .iter()
+            .filter(|c| c.attacking_obelisk)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+            .or(nearest),
+        TargetingPolicy::AttackingMe => candidates
+            .iter()
+            .filter(|c| c.attacking == Some(hero))
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+            .or(nearest),
     };
 
-    if attack.fire_damage > 0 {
-        let fire_damage = calc_damage(attack.fire_damage, defence.fire_armour);
-        result.damage += fire_damage;
-        result.burning = fire_damage > 0;
-    }
-
-    if attack.electricity_damage > 0 {
-        let electrical_damage = calc_damage(attack.electricity_damage, defence.electricity_armour);
-        result.damage += electrical_damage;
-        result.shocked = electrical_damage > 0;
-    }
-
-    if attack.poison_damage > 0 {
-        let poison_damage = calc_damage(attack.poison_damage, defence.poison_armour);
-        result.damage += poison_damage;
-        result.poisoned = poison_damage > 0;
-    }
-
-    if attack.frost_damage > 0 {
-        let frost_damage = calc_damage(attack.frost_damage, defence.frost_armour);
-        result.damage += frost_damage;
-        result.frozen = frost_damage > 0;
-    }
-
-    result
+    selected.map(|c| c.entity)
 }
 
 /// Loop through all players, find enemies within their range and attack one of them
-/// ignores incapacitated enemies
+/// ignores incapacitated and stunned players. Ranged heroes fire a projectile instead.
+/// Players keep attacking the same enemy until it dies or leaves range, then use their
+/// targeting policy to choose another
 pub fn player_auto_attack_system(
     game_time: Res<GameTime>,
     audio: Res<AudioOutput>,
     assets: Res<MaterialsAndTextures>,
-    mut player_query: Query<Without<Incapacitated, (&Player, &mut BaseAttack)>>,
-    mut enemy_query: Query<(&Enemy, &Transform, &mut Health, &Defence)>,
+    calculator: Res<CombatCalculator>,
+    map: Res<MapDefinition>,
+    mut vfx_events: ResMut<Events<SpawnVfxEvent>>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut projectile_events: ResMut<Events<FireProjectileEvent>>,
+    mut player_query: Query<
+        Without<
+            Incapacitated,
+            Without<
+                Stunned,
+                (
+                    Entity,
+                    &Player,
+                    &Transform,
+                    &mut BaseAttack,
+                    &mut AttackTarget,
+                    &mut AnimationStateMachine,
+                ),
+            >,
+        >,
+    >,
+    mut enemy_query: Query<(
+        Entity,
+        &Enemy,
+        &Transform,
+        &AttackTarget,
+        &BaseAttack,
+        &mut Health,
+        &Defence,
+        &ThreatTable<ThreatSource>,
+    )>,
 ) {
-    let player_y_pos = TARGET_LOCATIONS[0].1 + PLAYER_OFFSET_Y;
+    for (player_entity, player, player_tx, mut attack, mut target, mut animations) in
+        &mut player_query.iter()
+    {
+        let player_y_pos = map.target_location(player.current_lane).y() + PLAYER_OFFSET_Y;
 
-    for (player, mut attack) in &mut player_query.iter() {
-        // attack cooldown
-        if attack.next_attack > game_time.elapsed_time {
+        // moving lanes interrupts a swing
+        if player.is_moving {
+            attack.interrupt();
             continue;
         }
 
-        if player.is_moving {
+        // attack cooldown
+        if !attack.is_ready(game_time.elapsed_time) && !attack.is_winding_up() {
             continue;
         }
 
-        // just pick the first available target in the lane :shrug:
-        let mut target: Option<(Mut<Health>, &Defence)> = None;
-
-        let enemy_query_instance = &mut enemy_query.iter();
-        for (enemy, transform, health, defence) in enemy_query_instance {
-            if health.current_health <= 0. {
-                continue;
-            }
-
-            if player.current_lane != enemy.lane {
-                continue;
-            }
-
-            if (player_y_pos - transform.translation().y()).abs() > attack.attack_range {
-                continue;
-            }
-
-            target = Some((health, defence));
-            break;
+        let candidates: Vec<TargetCandidate> = enemy_query
+            .iter()
+            .iter()
+            .filter(|(_, enemy, transform, _, _, health, _, _)| {
+                health.current_health > 0.
+                    && player.current_lane == enemy.lane
+                    && (player_y_pos - transform.translation().y()).abs() <= attack.attack_range
+            })
+            .map(
+                |(entity, _, transform, enemy_target, enemy_attack, health, _, threat)| {
+                    TargetCandidate {
+                        entity,
+                        distance: (player_y_pos - transform.translation().y()).abs(),
+                        health: health.current_health,
+                        damage_per_second: (enemy_attack.min_attack_damage
+                            + enemy_attack.max_attack_damage)
+                            as f32
+                            / 2.
+                            / enemy_attack.attack_speed.value.max(0.1),
+                        threat: threat.threat(ThreatSource::Hero(player_entity)),
+                        attacking: enemy_target.entity,
+                        attacking_obelisk: enemy_target.is_obelisk,
+                    }
+                },
+            )
+            .collect();
+
+        // forget targets which have died or moved out of range, which interrupts the swing
+        if !candidates.iter().any(|c| Some(c.entity) == target.entity) {
+            attack.interrupt();
+            target.entity = select_target(player.targeting_policy, player_entity, &candidates);
         }
 
-        match target {
+        match target.entity {
             None => {
                 continue;
             }
-            Some((mut health, defence)) => {
-                let result = resolve_combat(&attack, defence);
-                health.target_health -= result.damage as f32;
+            Some(entity) => {
+                if attack.is_ready(game_time.elapsed_time) {
+                    attack.start_swing(game_time.elapsed_time);
+                    animations.trigger("attack");
+                }
+
+                // damage is dealt at the impact, after the wind up
+                if !attack.is_impact(game_time.elapsed_time) {
+                    continue;
+                }
 
-                audio.play(assets.clang_audio);
+                let position = enemy_query.get::<Transform>(entity).unwrap().translation();
+
+                match &attack.projectile {
+                    Some(spec) => {
+                        projectile_events.send(FireProjectileEvent::new(
+                            Combatant::Hero(player.player_id),
+                            &*attack,
+                            spec,
+                            player_tx.translation(),
+                            entity,
+                            position,
+                        ));
+                    }
+                    None => {
+                        let defence = enemy_query.get::<Defence>(entity).unwrap();
+                        let mut health = enemy_query.get_mut::<Health>(entity).unwrap();
+                        let result = calculator.resolve(&*attack, &*defence);
+                        let was_alive = health.target_health > 0.;
+                        health.target_health -= result.damage as f32;
+                        text_events.send(FloatingTextEvent::damage(&result, position));
+                        combat_events.send(CombatEvent::damage(
+                            game_time.elapsed_time,
+                            Combatant::Hero(player.player_id),
+                            Combatant::Enemy(entity),
+                            None,
+                            &result,
+                            was_alive && health.target_health <= 0.,
+                        ));
+
+                        audio.play(assets.clang_audio);
+
+                        if result.is_crit {
+                            vfx_events.send(SpawnVfxEvent::attached(
+                                "splatter",
+                                entity,
+                                Vec3::new(0., 0., 1.),
+                            ));
+                        }
+                    }
+                };
 
                 // println!(
                 //     "COMBAT! {:?}, new health: {} --> {}",
                 //     result, health.current_health, health.target_health
                 // );
-
-                // update cooldown
-                attack.next_attack = game_time.elapsed_time + attack.attack_speed.value;
             }
         }
     }
 }
 
+/// Interrupts the swings of stunned heroes and enemies, and lets them act again once the stun
+/// wears off. The attack and movement systems skip anything that is `Stunned`
+pub fn stun_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    mut stunned: Query<(Entity, &Stunned, &mut BaseAttack)>,
+    animations: Query<&mut AnimationStateMachine>,
+) {
+    for (entity, stun, mut attack) in &mut stunned.iter() {
+        let is_stunned = stun.end_time > game_time.elapsed_time;
+        match animations.get_mut::<AnimationStateMachine>(entity) {
+            Err(_) => {}
+            Ok(mut machine) => machine.set("stunned", is_stunned),
+        };
+
+        if is_stunned {
+            attack.interrupt();
+            continue;
+        }
+
+        commands.remove_one::<Stunned>(entity);
+    }
+}
+
+#[derive(Default)]
+pub struct ThreatEventListener {
+    pub combat_reader: EventReader<CombatEvent>,
+}
+
+/// Builds up each enemy's threat table from combat events and from who is nearby, then targets
+/// the source with the most threat. Heroes who leave the lane or are incapacitated are forgotten
 pub fn enemy_target_selection_system(
-    mut enemy_query: Query<(&Enemy, &mut AttackTarget, &Transform)>,
+    game_time: Res<GameTime>,
+    combat_events: Res<Events<CombatEvent>>,
+    mut listener: ResMut<ThreatEventListener>,
+    map: Res<MapDefinition>,
+    mut enemy_query: Query<(
+        Entity,
+        &Enemy,
+        &BaseAttack,
+        &Transform,
+        &mut ThreatTable<ThreatSource>,
+        &mut AttackTarget,
+    )>,
     mut player_query: Query<Without<Incapacitated, (Entity, &Player)>>,
 ) {
-    for (enemy, mut target, enemy_tx) in &mut enemy_query.iter() {
-        match target.entity {
-            // just continue here, the auto attack system will reset to None if the player moves lane
-            Some(_) => continue,
-            None => {
-                // not quite sure how to filter queries yet, so do this slightly weird way
-                let players_in_lane: Vec<Entity> = player_query
-                    .iter()
-                    .iter()
-                    .filter(|result| {
-                        return result.1.current_lane == enemy.lane;
-                    })
-                    .map(|(e, _)| {
-                        return e.clone();
-                    })
-                    .collect::<Vec<_>>();
-
-                // if nobody is in the lane, and within melee range of the obelisk target the obelisk
-                let num_players_in_lane = players_in_lane.len();
-                target.is_obelisk = num_players_in_lane == 0
-                    && (enemy_tx.translation().y() - OBELISK_Y).abs() < 105. + MELEE_RANGE;
-                if num_players_in_lane == 0 {
-                    continue;
+    // (entity, player id, lane)
+    let players: Vec<(Entity, u8, usize)> = player_query
+        .iter()
+        .iter()
+        .map(|(e, player)| (e, player.player_id, player.current_lane))
+        .collect();
+
+    for event in listener.combat_reader.iter(&combat_events) {
+        let (hero, hero_lane) = match event.attacker {
+            Combatant::Hero(id) => match players.iter().find(|(_, pid, _)| *pid == id) {
+                None => continue,
+                Some((e, _, lane)) => (*e, *lane),
+            },
+            _ => continue,
+        };
+
+        match (&event.kind, event.target) {
+            (CombatEventKind::Damage { result, .. }, Combatant::Enemy(target)) => {
+                match enemy_query.get_mut::<ThreatTable<ThreatSource>>(target) {
+                    Err(_) => {}
+                    Ok(mut threat) => threat.add(
+                        ThreatSource::Hero(hero),
+                        result.damage.max(0) as f32 * THREAT_PER_DAMAGE,
+                    ),
+                };
+            }
+            (CombatEventKind::Taunt(amount), Combatant::Enemy(target)) => {
+                match enemy_query.get_mut::<ThreatTable<ThreatSource>>(target) {
+                    Err(_) => {}
+                    Ok(mut threat) => threat.add(ThreatSource::Hero(hero), *amount),
+                };
+            }
+            (CombatEventKind::Heal(amount), _) => {
+                // healing angers every enemy in the healer's lane
+                for (_, enemy, _, _, mut threat, _) in &mut enemy_query.iter() {
+                    if enemy.lane == hero_lane {
+                        threat.add(ThreatSource::Hero(hero), amount * THREAT_PER_HEAL);
+                    }
                 }
+            }
+            _ => {}
+        };
+    }
+
+    for (_, enemy, attack, enemy_tx, mut threat, mut target) in &mut enemy_query.iter() {
+        // heroes can only be attacked while they are in the enemy's lane
+        threat.retain(|source| match source {
+            ThreatSource::Hero(hero) => players
+                .iter()
+                .any(|(e, _, lane)| e == hero && *lane == enemy.lane),
+            ThreatSource::Obelisk => true,
+        });
+
+        for (hero, _, lane) in players.iter() {
+            if *lane == enemy.lane {
+                threat.add(
+                    ThreatSource::Hero(*hero),
+                    HERO_PROXIMITY_THREAT * game_time.delta,
+                );
+            }
+        }
+
+        // the obelisk is just another source of threat once it is in range
+        if map.obelisk_in_reach(enemy_tx.translation().truncate(), attack.attack_range) {
+            threat.add(
+                ThreatSource::Obelisk,
+                OBELISK_PROXIMITY_THREAT * game_time.delta,
+            );
+        } else {
+            threat.remove(ThreatSource::Obelisk);
+        }
 
-                println!("Selected new target for enemy");
-                target.entity =
-                    Some(players_in_lane[RNG::usize_between(0, players_in_lane.len())].clone());
+        // decay after adding this frame's proximity threat, so it isn't forgotten before it
+        // can build up
+        threat.decay(game_time.delta);
 
-                // TODO could throttle checks here, e.g. if no players in the lane don't check every frame
+        let previous = target.entity;
+        match threat.update_target() {
+            None => {
+                target.entity = None;
+                target.is_obelisk = false;
+            }
+            Some(ThreatSource::Hero(hero)) => {
+                target.entity = Some(hero);
+                target.is_obelisk = false;
+            }
+            Some(ThreatSource::Obelisk) => {
+                target.entity = None;
+                target.is_obelisk = true;
             }
         };
+
+        if target.entity.is_some() && target.entity != previous {
+            println!("Selected new target for enemy");
+        }
     }
 }
 
@@ -177,26 +371,59 @@ pub fn enemy_auto_attack_system(
     game_time: Res<GameTime>,
     audio: Res<AudioOutput>,
     assets: Res<MaterialsAndTextures>,
+    calculator: Res<CombatCalculator>,
     mut player_score: ResMut<PlayerScore>,
-    mut enemy_query: Query<(&Enemy, &Transform, &mut AttackTarget, &mut BaseAttack)>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut projectile_events: ResMut<Events<FireProjectileEvent>>,
+    mut enemy_query: Query<
+        Without<
+            Stunned,
+            (
+                Entity,
+                &Enemy,
+                &Transform,
+                &mut AttackTarget,
+                &mut BaseAttack,
+            ),
+        >,
+    >,
     player_query: Query<(&Player, &Transform, &mut Health, &Defence)>,
 ) {
-    for (enemy, enemy_tx, mut target, mut attack) in &mut enemy_query.iter() {
+    for (entity, enemy, enemy_tx, mut target, mut attack) in &mut enemy_query.iter() {
         // attack cooldown
-        if attack.next_attack > game_time.elapsed_time {
+        if !attack.is_ready(game_time.elapsed_time) && !attack.is_winding_up() {
             continue;
         }
 
         // if we don't have a target, and aren't attacking the obelisk
         if target.entity.is_none() {
             if !target.is_obelisk || player_score.obelisk_health == 0 {
+                attack.interrupt();
                 continue;
             }
 
-            // if we are attacking the obelisk, and are in range, attack with defence of 0
+            if attack.is_ready(game_time.elapsed_time) {
+                attack.start_swing(game_time.elapsed_time);
+            }
+
+            if !attack.is_impact(game_time.elapsed_time) {
+                continue;
+            }
+
+            // if we are attacking the obelisk, and are in range, attack with defence of 0.
+            // The obelisk isn't an entity so ranged attacks against it land instantly
             let dummy_defence = Defence::default();
-            let result = resolve_combat(&attack, &dummy_defence);
+            let result = calculator.resolve(&*attack, &dummy_defence);
             let damage = result.damage as usize / OBELISK_DAMAGE_MODIFIER;
+            combat_events.send(CombatEvent::damage(
+                game_time.elapsed_time,
+                Combatant::Enemy(entity),
+                Combatant::Obelisk,
+                None,
+                &result,
+                damage >= player_score.obelisk_health,
+            ));
 
             // play audio annoucement
             if player_score.last_obelisk_damage - game_time.elapsed_time > 10. {
@@ -222,31 +449,72 @@ pub fn enemy_auto_attack_system(
 
         // check if the target is still in the same lane
         let target_player_result = player_query.get::<Player>(target.entity.unwrap());
-        match target_player_result {
+        let target_player_id = match target_player_result {
             Err(_) => {
                 target.entity = None;
+                attack.interrupt();
                 continue;
             }
             Ok(target_player) => {
                 if target_player.current_lane != enemy.lane {
                     target.entity = None;
+                    attack.interrupt();
                     continue;
                 }
+
+                target_player.player_id
             }
-        }
+        };
 
         // check the target is in range
         let target_entity = target.entity.unwrap();
         let transform = player_query.get_mut::<Transform>(target_entity).unwrap();
         if (enemy_tx.translation().y() - transform.translation().y()).abs() > attack.attack_range {
+            attack.interrupt();
             continue;
         }
+        let target_position = transform.translation();
+
+        if attack.is_ready(game_time.elapsed_time) {
+            attack.start_swing(game_time.elapsed_time);
+        }
+
+        // damage is dealt at the impact, after the wind up
+        if !attack.is_impact(game_time.elapsed_time) {
+            continue;
+        }
+
+        // ranged enemies throw a projectile, which resolves the combat when it lands
+        match &attack.projectile {
+            None => {}
+            Some(spec) => {
+                projectile_events.send(FireProjectileEvent::new(
+                    Combatant::Enemy(entity),
+                    &*attack,
+                    spec,
+                    enemy_tx.translation(),
+                    target_entity,
+                    target_position,
+                ));
+                continue;
+            }
+        };
 
         // now carry out the combat against the target player
         let mut health = player_query.get_mut::<Health>(target_entity).unwrap();
         let defence = player_query.get::<Defence>(target_entity).unwrap();
-        let result = resolve_combat(&attack, &defence);
+        let result = calculator.resolve(&*attack, &*defence);
+        let was_alive = health.target_health > 0.;
         health.target_health -= result.damage as f32;
+        text_events.send(FloatingTextEvent::damage(&result, target_position));
+        combat_events.send(CombatEvent::damage(
+            game_time.elapsed_time,
+            Combatant::Enemy(entity),
+            Combatant::Hero(target_player_id),
+            None,
+            &result,
+            was_alive && health.target_health <= 0.,
+        ));
 
         if health.target_health <= 0. {
             // stop targeting dead/incapacitated enemies
@@ -257,9 +525,6 @@ pub fn enemy_auto_attack_system(
         //     "COMBAT! {:?}, new health: {} --> {}",
         //     result, health.current_health, health.target_health
         // );
-
-        // update cooldown
-        attack.next_attack = game_time.elapsed_time + attack.attack_speed.value;
     }
 }
 
@@ -278,3 +543,112 @@ pub fn dead_enemy_removal_system(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectre_combat::damage::DamageType;
+    use spectre_core::BuffableStatistic;
+
+    fn attack(damage_type: DamageType, damage: i32) -> BaseAttack {
+        BaseAttack {
+            min_attack_damage: damage,
+            max_attack_damage: damage,
+            damage_type,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn damage_types_use_the_matching_armour() {
+        let defence = Defence {
+            base_armour: BuffableStatistic::new(10.),
+            fire_armour: 0,
+            ..Default::default()
+        };
+        let calculator = CombatCalculator::default();
+
+        let result = calculator.resolve(&attack(DamageType::Fire, 10), &defence);
+        assert_eq!(result.damage, 10);
+        assert!(result.burning);
+
+        let result = calculator.resolve(&attack(DamageType::Pure, 10), &defence);
+        assert_eq!(result.damage, 5);
+        assert!(!result.burning);
+    }
+
+    #[test]
+    fn targeting_policies() {
+        let hero = Entity::new(1);
+        let candidate = |id: u32, distance: f32, health: f32| TargetCandidate {
+            entity: Entity::new(id),
+            distance,
+            health,
+            damage_per_second: health / 10.,
+            threat: 0.,
+            attacking: None,
+            attacking_obelisk: false,
+        };
+
+        let mut candidates = vec![candidate(10, 5., 50.), candidate(11, 20., 20.)];
+        candidates.push(TargetCandidate {
+            attacking: Some(hero),
+            ..candidate(12, 30., 80.)
+        });
+
+        let select = |policy| select_target(policy, hero, &candidates);
+        assert_eq!(select(TargetingPolicy::Nearest), Some(Entity::new(10)));
+        assert_eq!(select(TargetingPolicy::LowestHealth), Some(Entity::new(11)));
+        assert_eq!(
+            select(TargetingPolicy::HighestThreat),
+            Some(Entity::new(12))
+        );
+        assert_eq!(select(TargetingPolicy::AttackingMe), Some(Entity::new(12)));
+        assert_eq!(
+            select(TargetingPolicy::AttackingObelisk),
+            Some(Entity::new(10))
+        );
+        assert_eq!(select_target(TargetingPolicy::Nearest, hero, &[]), None);
+
+        // once enemies build up threat against the hero it outranks damage per second
+        candidates[1].threat = 5.;
+        assert_eq!(
+            select_target(TargetingPolicy::HighestThreat, hero, &candidates),
+            Some(Entity::new(11))
+        );
+    }
+
+    #[test]
+    fn swings_wind_up_before_the_impact() {
+        let mut attack = BaseAttack {
+            attack_speed: BuffableStatistic::new(2.),
+            wind_up: 0.25,
+            ..Default::default()
+        };
+
+        assert!(attack.is_ready(0.));
+        attack.start_swing(0.);
+        assert!(!attack.is_ready(0.1));
+        assert!(!attack.is_impact(0.4));
+        assert!(attack.is_impact(0.5));
+        assert!(!attack.is_impact(0.6));
+        assert!(!attack.is_ready(1.9));
+        assert!(attack.is_ready(2.));
+
+        // interrupted swings never land
+        attack.start_swing(2.);
+        attack.interrupt();
+        assert!(!attack.is_impact(3.));
+        assert!(attack.is_ready(4.));
+    }
+
+    #[test]
+    fn adds_elemental_damage_to_the_base_attack() {
+        let mut base = attack(DamageType::Pure, 10);
+        base.frost_damage = 4;
+
+        let result = CombatCalculator::default().resolve(&base, &Defence::default());
+        assert_eq!(result.damage, 14);
+        assert!(result.frozen);
+    }
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use spectre_combat::damage::CombatResult;
+
+use crate::constants::OBELISK_DAMAGE_MODIFIER;
+
+/// Collects every `CombatEvent` into the `CombatLog` resource
+pub struct CombatLogPlugin;
+
+impl Plugin for CombatLogPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<CombatEvent>()
+            .init_resource::<CombatEventListener>()
+            .init_resource::<CombatLog>()
+            .add_system(combat_log_system.system());
+    }
+}
+
+/// Something which can take part in combat
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Combatant {
+    /// a hero, by player id
+    Hero(u8),
+    Enemy(Entity),
+    Obelisk,
+}
+
+#[derive(Clone, Debug)]
+pub enum CombatEventKind {
+    /// an attack, the result has the breakdown of the damage dealt
+    Damage {
+        result: CombatResult,
+        is_kill: bool,
+    },
+    Heal(f32),
+    /// an ability which draws the target's attention, with the amount of threat added
+    Taunt(f32),
+}
+
+/// Sent whenever one combatant damages, heals or taunts another
+#[derive(Clone, Debug)]
+pub struct CombatEvent {
+    /// the game time of the event
+    pub time: f32,
+    pub attacker: Combatant,
+    pub target: Combatant,
+
+    /// the ability that caused the event, or None for auto attacks
+    pub ability: Option<u16>,
+    pub kind: CombatEventKind,
+}
+
+impl CombatEvent {
+    pub fn damage(
+        time: f32,
+        attacker: Combatant,
+        target: Combatant,
+        ability: Option<u16>,
+        result: &CombatResult,
+        is_kill: bool,
+    ) -> Self {
+        CombatEvent {
+            time,
+            attacker,
+            target,
+            ability,
+            kind: CombatEventKind::Damage {
+                result: result.clone(),
+                is_kill,
+            },
+        }
+    }
+
+    pub fn heal(
+        time: f32,
+        healer: Combatant,
+        target: Combatant,
+        ability: Option<u16>,
+        amount: f32,
+    ) -> Self {
+        CombatEvent {
+            time,
+            attacker: healer,
+            target,
+            ability,
+            kind: CombatEventKind::Heal(amount),
+        }
+    }
+
+    pub fn taunt(
+        time: f32,
+        attacker: Combatant,
+        target: Combatant,
+        ability: Option<u16>,
+        threat: f32,
+    ) -> Self {
+        CombatEvent {
+            time,
+            attacker,
+            target,
+            ability,
+            kind: CombatEventKind::Taunt(threat),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct CombatEventListener {
+    pub combat_reader: EventReader<CombatEvent>,
+}
+
+/// The combat statistics for a single hero
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeroCombatSummary {
+    pub damage_dealt: i32,
+    pub damage_taken: i32,
+    pub healing_done: f32,
+    pub kills: usize,
+
+    /// the damage enemies would have done to the obelisk if they weren't attacking this hero
+    pub obelisk_damage_prevented: usize,
+}
+
+/// A resource with every combat event since the start of the game
+#[derive(Default)]
+pub struct CombatLog {
+    pub events: Vec<CombatEvent>,
+}
+
+impl CombatLog {
+    pub fn clear(&mut self) {
+        self.events.clear();
+    }
+
+    /// the ids of every hero in the log, in order
+    pub fn heroes(&self) -> Vec<u8> {
+        let mut heroes = Vec::new();
+        for event in self.events.iter() {
+            for combatant in [event.attacker, event.target].iter() {
+                match combatant {
+                    Combatant::Hero(id) if !heroes.contains(id) => heroes.push(*id),
+                    _ => {}
+                };
+            }
+        }
+
+        heroes.sort();
+        heroes
+    }
+
+    pub fn hero_summary(&self, hero: u8) -> HeroCombatSummary {
+        let mut summary = HeroCombatSummary::default();
+
+        for event in self.events.iter() {
+            let is_attacker = event.attacker == Combatant::Hero(hero);
+            let is_target = event.target == Combatant::Hero(hero);
+
+            match &event.kind {
+                CombatEventKind::Damage { result, is_kill } => {
+                    if is_attacker {
+                        summary.damage_dealt += result.damage;
+                        if *is_kill {
+                            summary.kills += 1;
+                        }
+                    }
+
+                    if is_target {
+                        summary.damage_taken += result.damage;
+                        summary.obelisk_damage_prevented +=
+                            result.damage.max(0) as usize / OBELISK_DAMAGE_MODIFIER;
+                    }
+                }
+                CombatEventKind::Heal(amount) => {
+                    if is_attacker {
+                        summary.healing_done += amount;
+                    }
+                }
+                CombatEventKind::Taunt(_) => {}
+            };
+        }
+
+        summary
+    }
+}
+
+fn combat_log_system(
+    events: Res<Events<CombatEvent>>,
+    mut listener: ResMut<CombatEventListener>,
+    mut log: ResMut<CombatLog>,
+) {
+    for event in listener.combat_reader.iter(&events) {
+        log.events.push(event.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hit(attacker: Combatant, target: Combatant, damage: i32, is_kill: bool) -> CombatEvent {
+        let result = CombatResult {
+            damage,
+            ..Default::default()
+        };
+        CombatEvent::damage(0., attacker, target, None, &result, is_kill)
+    }
+
+    #[test]
+    fn summarises_each_hero() {
+        let enemy = Combatant::Enemy(Entity::new(10));
+        let log = CombatLog {
+            events: vec![
+                hit(Combatant::Hero(1), enemy, 12, false),
+                hit(Combatant::Hero(1), enemy, 8, true),
+                hit(enemy, Combatant::Hero(0), 10, false),
+                hit(enemy, Combatant::Obelisk, 10, false),
+                CombatEvent::heal(0., Combatant::Hero(2), Combatant::Hero(0), Some(2000), 15.),
+            ],
+        };
+
+        assert_eq!(log.heroes(), vec![0, 1, 2]);
+
+        let summary = log.hero_summary(1);
+        assert_eq!(summary.damage_dealt, 20);
+        assert_eq!(summary.kills, 1);
+        assert_eq!(summary.damage_taken, 0);
+
+        let summary = log.hero_summary(0);
+        assert_eq!(summary.damage_taken, 10);
+        assert_eq!(
+            summary.obelisk_damage_prevented,
+            10 / OBELISK_DAMAGE_MODIFIER
+        );
+
+        assert_eq!(log.hero_summary(2).healing_done, 15.);
+    }
+}
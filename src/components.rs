@@ -1,7 +1,13 @@
 use bevy::prelude::*;
+use spectre_combat::{
+    damage::{AttackStats, DamageType, DefenceStats, Resistances},
+    threat::ThreatTable,
+};
 use spectre_core::*;
 
-use crate::{abilities::AbilityDetail, constants::*};
+use crate::{
+    abilities::AbilityDetail, constants::*, hero_classes::HeroClass, projectiles::ProjectileSpec,
+};
 
 #[derive(Bundle)]
 pub struct EnemyBundle {
@@ -9,7 +15,9 @@ pub struct EnemyBundle {
     pub defence: Defence,
     pub enemy: Enemy,
     pub health: Health,
+    pub movement: Movement,
     pub attack_target: AttackTarget,
+    pub threat: ThreatTable<ThreatSource>,
 }
 
 #[derive(Bundle)]
@@ -20,23 +28,38 @@ pub struct PlayerBundle {
     pub attack: BaseAttack,
     pub defence: Defence,
     pub player: Player,
+    pub class: HeroClass,
     pub actions: PlayerAbilityActions,
-    // pub attack_target: AttackTarget, // TODO: post jam
+    pub attack_target: AttackTarget,
 }
 
 /// Contains data about an enemy unit
 pub struct Enemy {
     pub lane: usize,
-    pub target: Vec2,
+
+    /// the index of the point in the lane's path the enemy is walking to
+    pub waypoint: usize,
+
+    /// how far to the side of the lane's path the enemy walks, so groups don't stack up
+    pub offset: Vec2,
+
+    /// how far before the defend point to stop while heroes are in the lane, i.e. so ranged
+    /// enemies stay back
+    pub stand_off: f32,
+
+    /// set when the enemy stops to attack the obelisk
+    pub at_obelisk: bool,
     pub xp_reward: usize,
 }
 
 impl Enemy {
     pub fn new(lane: usize, xp_reward: usize) -> Self {
-        let target_loc = Vec2::from(TARGET_LOCATIONS[lane]);
         Enemy {
             lane,
-            target: target_loc,
+            waypoint: 1,
+            offset: Vec2::zero(),
+            stand_off: 0.,
+            at_obelisk: false,
             xp_reward,
         }
     }
@@ -48,6 +71,7 @@ pub struct Player {
     pub target_lane: usize,
     pub is_moving: bool,
     pub abilities: Vec<u16>,
+    pub targeting_policy: TargetingPolicy,
 }
 
 impl Player {
@@ -64,6 +88,46 @@ impl Player {
     }
 }
 
+/// How a hero picks a new auto attack target from the enemies in range
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TargetingPolicy {
+    Nearest,
+    LowestHealth,
+    /// the enemy with the most threat against the hero, then the most damage per second
+    HighestThreat,
+    AttackingObelisk,
+    AttackingMe,
+}
+
+impl Default for TargetingPolicy {
+    fn default() -> Self {
+        TargetingPolicy::Nearest
+    }
+}
+
+impl TargetingPolicy {
+    /// the policy after this one, used to cycle through policies in the UI
+    pub fn next(&self) -> Self {
+        match self {
+            TargetingPolicy::Nearest => TargetingPolicy::LowestHealth,
+            TargetingPolicy::LowestHealth => TargetingPolicy::HighestThreat,
+            TargetingPolicy::HighestThreat => TargetingPolicy::AttackingObelisk,
+            TargetingPolicy::AttackingObelisk => TargetingPolicy::AttackingMe,
+            TargetingPolicy::AttackingMe => TargetingPolicy::Nearest,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TargetingPolicy::Nearest => "Nearest",
+            TargetingPolicy::LowestHealth => "Weakest",
+            TargetingPolicy::HighestThreat => "Most threat",
+            TargetingPolicy::AttackingObelisk => "Protect obelisk",
+            TargetingPolicy::AttackingMe => "Defend self",
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct AbilityActionDetails {
     pub action: Option<u16>,
@@ -83,11 +147,105 @@ pub struct BaseAttack {
     pub next_attack: f32,
     pub min_attack_damage: i32,
     pub max_attack_damage: i32,
+
+    /// the type of damage dealt by the min / max attack damage
+    pub damage_type: DamageType,
     pub crit_chance: f32,
     pub fire_damage: i32,
     pub electricity_damage: i32,
     pub poison_damage: i32,
     pub frost_damage: i32,
+
+    /// ranged attacks fire a projectile, otherwise damage is applied instantly
+    pub projectile: Option<ProjectileSpec>,
+
+    /// the fraction of each attack spent winding up before the impact, the rest is recovery
+    pub wind_up: f32,
+    pub phase: AttackPhase,
+}
+
+/// The phases of a swing. A swing starts when the attack is ready, winds up and then deals
+/// its damage at the impact before recovering until `next_attack`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AttackPhase {
+    Ready,
+    WindUp { impact_at: f32 },
+    Recovery,
+}
+
+impl Default for AttackPhase {
+    fn default() -> Self {
+        AttackPhase::Ready
+    }
+}
+
+impl BaseAttack {
+    /// true if a new swing can be started
+    pub fn is_ready(&self, elapsed_time: f32) -> bool {
+        match self.phase {
+            AttackPhase::WindUp { .. } => false,
+            _ => self.next_attack <= elapsed_time,
+        }
+    }
+
+    pub fn is_winding_up(&self) -> bool {
+        match self.phase {
+            AttackPhase::WindUp { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// starts a swing. The whole swing takes `attack_speed` seconds, so attack speed buffs
+    /// scale every phase
+    pub fn start_swing(&mut self, elapsed_time: f32) {
+        let duration = self.attack_speed.value;
+        self.phase = AttackPhase::WindUp {
+            impact_at: elapsed_time + duration * self.wind_up,
+        };
+        self.next_attack = elapsed_time + duration;
+    }
+
+    /// true if the swing lands this frame, at which point it moves into recovery
+    pub fn is_impact(&mut self, elapsed_time: f32) -> bool {
+        match self.phase {
+            AttackPhase::WindUp { impact_at } if impact_at <= elapsed_time => {
+                self.phase = AttackPhase::Recovery;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// cancels a swing that hasn't landed yet. The swing is wasted, so the next one
+    /// still waits for `next_attack`
+    pub fn interrupt(&mut self) {
+        if self.is_winding_up() {
+            self.phase = AttackPhase::Recovery;
+        }
+    }
+}
+
+impl AttackStats for BaseAttack {
+    fn damage_range(&self) -> (i32, i32) {
+        (self.min_attack_damage, self.max_attack_damage)
+    }
+
+    fn damage_type(&self) -> DamageType {
+        self.damage_type
+    }
+
+    fn crit_chance(&self) -> f32 {
+        self.crit_chance
+    }
+
+    fn bonus_damage(&self) -> Vec<(DamageType, i32)> {
+        vec![
+            (DamageType::Fire, self.fire_damage),
+            (DamageType::Shock, self.electricity_damage),
+            (DamageType::Poison, self.poison_damage),
+            (DamageType::Ice, self.frost_damage),
+        ]
+    }
 }
 
 #[derive(Default)]
@@ -97,6 +255,33 @@ pub struct Defence {
     pub electricity_armour: i32,
     pub poison_armour: i32,
     pub frost_armour: i32,
+    pub resistances: Resistances,
+    pub dodge_chance: f32,
+    pub block_chance: f32,
+}
+
+impl DefenceStats for Defence {
+    fn armour_for(&self, damage_type: DamageType) -> i32 {
+        match damage_type {
+            DamageType::Pure => self.base_armour.value as i32,
+            DamageType::Fire => self.fire_armour,
+            DamageType::Shock => self.electricity_armour,
+            DamageType::Poison => self.poison_armour,
+            DamageType::Ice => self.frost_armour,
+        }
+    }
+
+    fn resistances(&self) -> Option<&Resistances> {
+        Some(&self.resistances)
+    }
+
+    fn dodge_chance(&self) -> f32 {
+        self.dodge_chance
+    }
+
+    fn block_chance(&self) -> f32 {
+        self.block_chance
+    }
 }
 
 pub struct HealthBar {
@@ -117,6 +302,13 @@ impl Default for AttackTarget {
     }
 }
 
+/// Something an enemy can build up threat against
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThreatSource {
+    Hero(Entity),
+    Obelisk,
+}
+
 /// A resource to store what wave is currently active
 pub struct CurrentWave {
     // the index of the wave to spawn
@@ -153,12 +345,19 @@ pub struct PlayerLaneChangeLink {
     pub delta: i8,
 }
 
+/// Links a button (and its text) to cycling a player's targeting policy
+pub struct PlayerTargetingLink {
+    pub player_id: u8,
+}
+
 pub struct ObeliskStatusTextUiLink;
 pub struct ObeliskStatusImageUiLink;
 
 pub struct PlayerScore {
     pub xp: usize,
     pub obelisk_health: usize,
+    /// the obelisk's health at the start of each day, set by the difficulty
+    pub max_obelisk_health: usize,
     pub last_obelisk_damage: f32,
     pub game_over: bool,
 }
@@ -167,7 +366,8 @@ impl FromResources for PlayerScore {
     fn from_resources(_: &Resources) -> Self {
         PlayerScore {
             xp: 0,
-            obelisk_health: 1000,
+            obelisk_health: OBELISK_HEALTH,
+            max_obelisk_health: OBELISK_HEALTH,
             last_obelisk_damage: 0.,
             game_over: false,
         }
@@ -203,6 +403,11 @@ pub struct AbilityPurchaseInteraction {
     pub ability_id: u16,
 }
 
+/// Stops a hero or enemy moving and attacking until `end_time`, see `stun_system`
+pub struct Stunned {
+    pub end_time: f32,
+}
+
 #[derive(Default)]
 pub struct Incapacitated {
     /// set to 0 to make indefinite
@@ -212,6 +417,8 @@ pub struct Incapacitated {
 
 pub struct SpawnedAbility {
     pub lane: usize,
+    pub player_id: u8,
+    pub ability_id: u16,
     pub effects: Vec<AbilityDetail>,
 
     // NOTE: this is a stop gap as commands.despawn in abilities::execute_abilities panics.
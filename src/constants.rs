@@ -36,6 +36,17 @@ pub const SPLATTER_ID: u128 = 32409680346893434011111;
 pub const FLAME_WALL_ID: u128 = 24098760324850394860349856;
 pub const HEAL_ID: u128 = 3409856304958034598;
 
+/// texture atlases, built from the Aseprite sidecar files
+pub const CHARACTER_1_ATLAS: u128 = 2340965832048509026;
+pub const CHARACTER_2_ATLAS: u128 = 135120965832048509026;
+pub const CHARACTER_3_ATLAS: u128 = 768909245132048509026;
+pub const ENEMY_WOLF_ATLAS: u128 = 3456909345645132345626;
+pub const ENEMY_BEAR_ATLAS: u128 = 32406238029835205826735;
+pub const ENEMY_TROLL_ATLAS: u128 = 12637462356347113253;
+pub const SPLATTER_ATLAS: u128 = 32409680346893434011112;
+pub const FLAME_WALL_ATLAS: u128 = 24098760324850394860349857;
+pub const HEAL_ATLAS: u128 = 3409856304958034599;
+
 /// The margin for 9-patch UI assets (16x16)
 pub const UI_SPRITE_MARGIN: f32 = 7.;
 
@@ -48,11 +59,6 @@ pub const GAME_ELEMENT_LAYER: f32 = 3.;
 // pub const GAME_OFFSET_X: f32 = -640.; // negative half RESOLUTION_X
 // pub const GAME_OFFSET_Y: f32 = 360.; // half RESOLUTION_Y
 
-// PROBABLY SHOULDN'T BE HARDCODED, BUT GAME JAM
-pub const OBELISK_Y: f32 = -300.;
-pub const SPAWN_LOCATIONS: [(f32, f32); 3] = [(-480., 365.), (-180., 365.), (120., 365.)];
-pub const TARGET_LOCATIONS: [(f32, f32); 3] = [(-480., -200.), (-180., -200.), (120., -200.)];
-
 /// how far the players set up from the target location
 pub const PLAYER_OFFSET_Y: f32 = 40.;
 pub const PLAYER_OFFSET_X: f32 = 40.;
@@ -60,12 +66,23 @@ pub const PLAYER_OFFSET_X: f32 = 40.;
 /// the range that is considered "melee"
 pub const MELEE_RANGE: f32 = PLAYER_OFFSET_Y + 2.;
 
-/// PROBABLY SHOULDN'T BE HARDCODED, BUT GAME JAM
-pub const ENEMY_SPEED: f32 = 60.;
+/// the obelisk's health on normal difficulty
+pub const OBELISK_HEALTH: usize = 1000;
+
+/// how far above or below the obelisk enemies can attack it from, on top of their range
+pub const OBELISK_REACH: f32 = 105.;
 
-pub const MIN_LANE: usize = 0;
-pub const MAX_LANE: usize = 2;
+/// the shortest time between warnings that enemies have reached the obelisk, in seconds
+pub const OBELISK_WARNING_INTERVAL: f32 = 10.;
 
 /// reduction in damage when attacking the obelisk
 pub const OBELISK_DAMAGE_MODIFIER: usize = 5;
+
+/// threat generated for each point of damage or healing
+pub const THREAT_PER_DAMAGE: f32 = 1.;
+pub const THREAT_PER_HEAL: f32 = 0.5;
+
+/// threat generated each second by heroes in an enemy's lane, and the obelisk when in range
+pub const HERO_PROXIMITY_THREAT: f32 = 2.;
+pub const OBELISK_PROXIMITY_THREAT: f32 = 1.;
 pub const DEFAULT_GAME_SPEED: f32 = 1.;
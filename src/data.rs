@@ -1,16 +1,46 @@
 use bevy::prelude::*;
-use spectre_loaders::data_loaders::DataFileLoader;
+use spectre_animations::prelude::VfxDatabase;
+use spectre_loaders::data_loaders::DataResourcePlugin;
 
-use crate::abilities::ability_data::AbilityDatabase;
+use crate::{
+    abilities::ability_data::{AbilityDatabase, ABILITY_DATA_FILES},
+    enemy_factory::{EnemyDatabase, ENEMY_DATA_FILES},
+    hero_classes::{HeroClassDatabase, HERO_CLASS_DATA_FILES},
+    map::{MapDatabase, MAP_DATA_FILES},
+    waves::{WaveDatabase, WAVE_DATA_FILES},
+};
 
+/// The visual effect files to load, merged in this order
+pub const VFX_DATA_FILES: [&str; 1] = ["assets/data/effects.vfr"];
+
+/// Loads game data from RON files into resources, reloading them when the files change
 pub struct DataFileLoaderPlugin;
 
 impl Plugin for DataFileLoaderPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        // not used as I'm hard coding in abilities
-        app.add_asset::<AbilityDatabase>()
-            .add_asset_loader_from_instance::<AbilityDatabase, DataFileLoader>(
-                DataFileLoader::from_extensions(vec!["abr"]),
-            );
+        app.add_plugin(DataResourcePlugin::<AbilityDatabase>::new(
+            ABILITY_DATA_FILES.to_vec(),
+            vec!["abr"],
+        ))
+        .add_plugin(DataResourcePlugin::<VfxDatabase>::new(
+            VFX_DATA_FILES.to_vec(),
+            vec!["vfr"],
+        ))
+        .add_plugin(DataResourcePlugin::<WaveDatabase>::new(
+            WAVE_DATA_FILES.to_vec(),
+            vec!["wvr"],
+        ))
+        .add_plugin(DataResourcePlugin::<EnemyDatabase>::new(
+            ENEMY_DATA_FILES.to_vec(),
+            vec!["enr"],
+        ))
+        .add_plugin(DataResourcePlugin::<HeroClassDatabase>::new(
+            HERO_CLASS_DATA_FILES.to_vec(),
+            vec!["hcr"],
+        ))
+        .add_plugin(DataResourcePlugin::<MapDatabase>::new(
+            MAP_DATA_FILES.to_vec(),
+            vec!["mpr"],
+        ));
     }
 }
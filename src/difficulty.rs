@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_random::RNG;
+
+use crate::components::EnemyBundle;
+
+/// Where the multipliers for the custom difficulty are read from, if it exists
+pub const CUSTOM_DIFFICULTY_FILE: &str = "custom_difficulty.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub enum DifficultyPreset {
+    Easy,
+    Normal,
+    Hard,
+    Custom,
+}
+
+impl Default for DifficultyPreset {
+    fn default() -> Self {
+        DifficultyPreset::Normal
+    }
+}
+
+impl DifficultyPreset {
+    /// the preset after this one, used to cycle through presets in the menu
+    pub fn next(&self) -> Self {
+        match self {
+            DifficultyPreset::Easy => DifficultyPreset::Normal,
+            DifficultyPreset::Normal => DifficultyPreset::Hard,
+            DifficultyPreset::Hard => DifficultyPreset::Custom,
+            DifficultyPreset::Custom => DifficultyPreset::Easy,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DifficultyPreset::Easy => "Easy",
+            DifficultyPreset::Normal => "Normal",
+            DifficultyPreset::Hard => "Hard",
+            DifficultyPreset::Custom => "Custom",
+        }
+    }
+}
+
+/// Multipliers applied to enemies, waves and the obelisk when they are spawned
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DifficultyModifiers {
+    pub enemy_health: f32,
+    pub enemy_damage: f32,
+    pub xp_reward: f32,
+    /// scales the number of enemies in each spawn group
+    pub wave_size: f32,
+    pub obelisk_health: f32,
+}
+
+impl Default for DifficultyModifiers {
+    fn default() -> Self {
+        DifficultyModifiers {
+            enemy_health: 1.,
+            enemy_damage: 1.,
+            xp_reward: 1.,
+            wave_size: 1.,
+            obelisk_health: 1.,
+        }
+    }
+}
+
+impl DifficultyModifiers {
+    pub fn easy() -> Self {
+        DifficultyModifiers {
+            enemy_health: 0.75,
+            enemy_damage: 0.75,
+            xp_reward: 1.25,
+            wave_size: 0.75,
+            obelisk_health: 1.5,
+        }
+    }
+
+    pub fn hard() -> Self {
+        DifficultyModifiers {
+            enemy_health: 1.3,
+            enemy_damage: 1.25,
+            xp_reward: 0.9,
+            wave_size: 1.5,
+            obelisk_health: 0.75,
+        }
+    }
+
+    /// scales a freshly built enemy's health, damage and XP reward
+    pub fn apply_to_enemy(&self, bundle: &mut EnemyBundle) {
+        let health = bundle.health.max_health.base_value * self.enemy_health;
+        bundle.health.max_health.set_base(health);
+        bundle.health.current_health = health;
+        bundle.health.target_health = health;
+
+        // the elemental damage is added on top of the base attack, so it scales too
+        let scale = |damage: i32| (damage as f32 * self.enemy_damage).round() as i32;
+        let attack = &mut bundle.attack;
+        attack.min_attack_damage = scale(attack.min_attack_damage);
+        attack.max_attack_damage = scale(attack.max_attack_damage);
+        attack.fire_damage = scale(attack.fire_damage);
+        attack.electricity_damage = scale(attack.electricity_damage);
+        attack.poison_damage = scale(attack.poison_damage);
+        attack.frost_damage = scale(attack.frost_damage);
+
+        bundle.enemy.xp_reward = (bundle.enemy.xp_reward as f32 * self.xp_reward).round() as usize;
+    }
+
+    /// the number of enemies to spawn for a group. Fractions are rounded up or down at random
+    /// so small groups still change size on average
+    pub fn group_size(&self, count: usize) -> usize {
+        let scaled = count as f32 * self.wave_size.max(0.);
+        let whole = scaled.floor();
+        let extra = if RNG::test((scaled - whole).min(1.)) {
+            1
+        } else {
+            0
+        };
+
+        whole as usize + extra
+    }
+
+    pub fn obelisk_health(&self, base_health: usize) -> usize {
+        ((base_health as f32 * self.obelisk_health).round() as usize).max(1)
+    }
+}
+
+/// A resource with the selected difficulty
+pub struct Difficulty {
+    pub preset: DifficultyPreset,
+
+    /// the multipliers used for the custom preset
+    pub custom: DifficultyModifiers,
+}
+
+impl FromResources for Difficulty {
+    fn from_resources(_: &Resources) -> Self {
+        let custom = match std::fs::read_to_string(CUSTOM_DIFFICULTY_FILE) {
+            Err(_) => DifficultyModifiers::default(),
+            Ok(contents) => match ron::de::from_str(&contents) {
+                Err(e) => {
+                    println!("Unable to read custom difficulty, using normal - {:?}", e);
+                    DifficultyModifiers::default()
+                }
+                Ok(custom) => custom,
+            },
+        };
+
+        Difficulty {
+            preset: DifficultyPreset::default(),
+            custom,
+        }
+    }
+}
+
+impl Difficulty {
+    /// the multipliers for the selected preset
+    pub fn modifiers(&self) -> DifficultyModifiers {
+        match self.preset {
+            DifficultyPreset::Easy => DifficultyModifiers::easy(),
+            DifficultyPreset::Normal => DifficultyModifiers::default(),
+            DifficultyPreset::Hard => DifficultyModifiers::hard(),
+            DifficultyPreset::Custom => self.custom.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy_factory::{get_enemy_bundle, EnemyDatabase, ENEMY_DATA_FILES};
+    use spectre_loaders::data_loaders::load_merged;
+
+    #[test]
+    fn scales_enemies_and_waves() {
+        let enemies = load_merged::<EnemyDatabase>(&ENEMY_DATA_FILES);
+        let mut bundle = get_enemy_bundle(enemies.get("wolf").unwrap(), 0);
+        bundle.attack.frost_damage = 4;
+        DifficultyModifiers::hard().apply_to_enemy(&mut bundle);
+        assert!((bundle.health.max_health.value - 39.).abs() < 0.01);
+        assert_eq!(bundle.health.current_health, bundle.health.max_health.value);
+        assert_eq!(bundle.attack.max_attack_damage, 6);
+        assert_eq!(bundle.attack.frost_damage, 5);
+
+        let normal = DifficultyModifiers::default();
+        assert_eq!(normal.group_size(3), 3);
+        assert_eq!(normal.obelisk_health(1000), 1000);
+
+        let hard = DifficultyModifiers::hard();
+        assert_eq!(hard.group_size(4), 6);
+        assert_eq!(hard.obelisk_health(1000), 750);
+    }
+}
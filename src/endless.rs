@@ -0,0 +1,210 @@
+use spectre_random::SeededRNG;
+
+use crate::{
+    difficulty::DifficultyPreset,
+    enemy_factory::EnemyDatabase,
+    waves::{SpawnGroup, SpawnPattern, WaveDatabase, WaveDefinition},
+};
+
+/// The difficulty budget for the first day of an endless run
+pub const ENDLESS_BASE_BUDGET: usize = 10;
+
+/// How much the difficulty budget grows each day
+pub const ENDLESS_BUDGET_PER_DAY: usize = 6;
+
+/// The number of waves generated each day
+pub const ENDLESS_WAVES_PER_DAY: usize = 5;
+
+/// A resource which decides where the waves each day come from
+pub enum GameMode {
+    /// play through the waves from the wave files
+    Story,
+    /// generate waves forever, until the obelisk falls
+    Endless(EndlessRun),
+}
+
+impl Default for GameMode {
+    fn default() -> Self {
+        GameMode::Story
+    }
+}
+
+impl GameMode {
+    /// the waves to play today
+    pub fn waves<'a>(&'a self, wave_data: &'a WaveDatabase) -> &'a [WaveDefinition] {
+        match self {
+            GameMode::Story => &wave_data.waves,
+            GameMode::Endless(run) => &run.waves,
+        }
+    }
+}
+
+/// The state of an endless run. The seed is kept so a run can be replayed
+pub struct EndlessRun {
+    /// the current day, starting from 1
+    pub day: usize,
+    pub seed: u64,
+    rng: SeededRNG,
+
+    /// the difficulty the run was started on, recorded with its high score
+    pub difficulty: DifficultyPreset,
+
+    /// the waves generated for the current day, empty until the day starts
+    pub waves: Vec<WaveDefinition>,
+}
+
+impl EndlessRun {
+    pub fn new(seed: u64, difficulty: DifficultyPreset) -> Self {
+        println!(
+            "Starting endless run with seed {} on {}",
+            seed,
+            difficulty.name()
+        );
+        EndlessRun {
+            day: 1,
+            seed,
+            rng: SeededRNG::new(seed),
+            difficulty,
+            waves: vec![],
+        }
+    }
+
+    pub fn days_survived(&self) -> usize {
+        self.day - 1
+    }
+
+    /// generates today's waves if they haven't been generated yet
+    pub fn start_day(&mut self, lane_count: usize, hero_lanes: &[usize], enemies: &EnemyDatabase) {
+        if !self.waves.is_empty() {
+            return;
+        }
+
+        println!("Generating waves for endless day {}", self.day);
+        self.waves = generate_day(self.day, lane_count, hero_lanes, enemies, &mut self.rng);
+    }
+
+    /// moves on to the next day, the waves are generated when it starts
+    pub fn next_day(&mut self) {
+        self.day += 1;
+        self.waves.clear();
+    }
+}
+
+/// the total difficulty budget spent on a day's waves
+pub fn day_budget(day: usize) -> usize {
+    ENDLESS_BASE_BUDGET + ENDLESS_BUDGET_PER_DAY * (day.max(1) - 1)
+}
+
+/// picks a lane for an enemy, favouring lanes with fewer heroes in them
+fn pick_lane(lane_count: usize, hero_lanes: &[usize], rng: &mut SeededRNG) -> usize {
+    let weights: Vec<f32> = (0..lane_count.max(1))
+        .map(|lane| 1. / (1 + hero_lanes.iter().filter(|l| **l == lane).count()) as f32)
+        .collect();
+
+    let mut roll = rng.next() * weights.iter().sum::<f32>();
+    for (idx, weight) in weights.iter().enumerate() {
+        if roll < *weight {
+            return idx;
+        }
+        roll -= weight;
+    }
+
+    weights.len() - 1
+}
+
+/// generates the waves for a day of an endless run. The day's budget is split across the
+/// waves so each wave is a little harder than the last, and spent on enemies by their cost
+pub fn generate_day(
+    day: usize,
+    lane_count: usize,
+    hero_lanes: &[usize],
+    enemies: &EnemyDatabase,
+    rng: &mut SeededRNG,
+) -> Vec<WaveDefinition> {
+    let budget = day_budget(day);
+    let ramp_total: usize = (1..=ENDLESS_WAVES_PER_DAY).sum();
+
+    // sorted so the same seed always gives the same waves
+    let mut costs: Vec<(&String, usize)> = enemies
+        .enemies
+        .iter()
+        .filter(|(_, enemy)| enemy.cost > 0)
+        .map(|(id, enemy)| (id, enemy.cost))
+        .collect();
+    costs.sort();
+
+    (1..=ENDLESS_WAVES_PER_DAY)
+        .map(|wave_number| {
+            let mut remaining = (budget * wave_number / ramp_total).max(1);
+            let mut groups: Vec<SpawnGroup> = vec![];
+
+            loop {
+                let affordable: Vec<&(&String, usize)> = costs
+                    .iter()
+                    .filter(|(_, cost)| *cost <= remaining)
+                    .collect();
+
+                if affordable.is_empty() {
+                    break;
+                }
+
+                let (enemy, cost) = affordable[rng.usize_between(0, affordable.len())];
+                remaining -= cost;
+
+                groups.push(SpawnGroup {
+                    enemy: enemy.to_string(),
+                    lane: pick_lane(lane_count, hero_lanes, rng),
+                    count: 1,
+                    interval: 0.,
+                    delay: rng.f32_between(0., 6.),
+                    pattern: SpawnPattern::Trickle,
+                });
+            }
+
+            WaveDefinition {
+                post_wave_delay: 10. + groups.len() as f32,
+                groups,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy_factory::ENEMY_DATA_FILES;
+    use spectre_loaders::data_loaders::load_merged;
+
+    fn spent(waves: &[WaveDefinition], enemies: &EnemyDatabase) -> usize {
+        waves
+            .iter()
+            .flat_map(|wave| wave.groups.iter())
+            .map(|group| enemies.get(&group.enemy).unwrap().cost * group.count)
+            .sum()
+    }
+
+    #[test]
+    fn generated_days_get_harder_and_repeat_for_a_seed() {
+        let enemies = load_merged::<EnemyDatabase>(&ENEMY_DATA_FILES);
+        let first = generate_day(1, 3, &[], &enemies, &mut SeededRNG::new(12));
+        let tenth = generate_day(10, 3, &[], &enemies, &mut SeededRNG::new(12));
+        assert_eq!(first.len(), ENDLESS_WAVES_PER_DAY);
+        assert!(spent(&first, &enemies) <= day_budget(1));
+        assert!(spent(&tenth, &enemies) > spent(&first, &enemies));
+
+        let replayed = generate_day(10, 3, &[], &enemies, &mut SeededRNG::new(12));
+        assert_eq!(spent(&tenth, &enemies), spent(&replayed, &enemies));
+    }
+
+    #[test]
+    fn favours_undefended_lanes() {
+        let mut rng = SeededRNG::new(3);
+        let lanes: Vec<usize> = (0..300)
+            .map(|_| pick_lane(3, &[0, 0, 2, 2], &mut rng))
+            .collect();
+
+        let undefended = lanes.iter().filter(|lane| **lane == 1).count();
+        assert!(undefended > lanes.iter().filter(|lane| **lane == 0).count());
+        assert!(undefended > lanes.iter().filter(|lane| **lane == 2).count());
+    }
+}
@@ -0,0 +1,608 @@
+//! Optional behaviours for enemies, added as components when an enemy spawns based on the
+//! `behaviours` and `boss_phases` in its definition.
+use bevy::prelude::*;
+use spectre_animations::prelude::SpawnVfxEvent;
+use spectre_combat::{damage::CombatCalculator, threat::ThreatTable};
+use spectre_core::{Buff, Health, Movement};
+use spectre_time::GameTime;
+
+use crate::{
+    abilities::{ability_data::AbilityDatabase, AbilityDetail, BuffType},
+    combat_log::{CombatEvent, Combatant},
+    components::*,
+    enemy_factory::{BossPhase, EnemyBehaviour, EnemyDefinition},
+    floating_text::FloatingTextEvent,
+    map::MapDefinition,
+};
+
+/// How long a charger sprints for before checking for low health heroes again
+pub const CHARGE_DURATION: f32 = 1.;
+
+/// Threat a charger adds to the hero it is charging, so it switches targets
+pub const CHARGE_THREAT: f32 = 50.;
+
+/// How long a boss buff lasts when its ability doesn't give a duration
+pub const BOSS_BUFF_DURATION: f32 = 10.;
+
+pub struct EnemyBehaviourPlugin;
+
+impl Plugin for EnemyBehaviourPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(enemy_buff_system.system())
+            .add_system(charger_system.system())
+            .add_system(support_system.system())
+            .add_system(lane_switch_system.system())
+            .add_system(boss_system.system());
+    }
+}
+
+pub struct Charger {
+    pub speed: f32,
+    pub health_threshold: f32,
+    pub charge_until: f32,
+}
+
+pub struct Support {
+    pub heal: f32,
+    pub armour: f32,
+    pub radius: f32,
+    pub cooldown: f32,
+    pub next_pulse: f32,
+}
+
+pub struct LaneSwitcher {
+    pub interval: f32,
+    pub next_check: f32,
+}
+
+pub struct Boss {
+    /// ordered from the highest health fraction to the lowest
+    pub phases: Vec<BossPhase>,
+    pub current_phase: Option<usize>,
+    pub next_ability: f32,
+    pub next_ability_idx: usize,
+}
+
+impl Boss {
+    pub fn new(mut phases: Vec<BossPhase>) -> Self {
+        phases.sort_by(|a, b| b.health_fraction.total_cmp(&a.health_fraction));
+        Boss {
+            phases,
+            current_phase: None,
+            next_ability: 0.,
+            next_ability_idx: 0,
+        }
+    }
+
+    /// moves on to the last phase whose health threshold has been reached, returning
+    /// the new phase if it changed
+    pub fn update_phase(&mut self, health_fraction: f32, elapsed_time: f32) -> Option<&BossPhase> {
+        let phase = self
+            .phases
+            .iter()
+            .rposition(|phase| health_fraction <= phase.health_fraction);
+
+        if phase.is_none() || phase <= self.current_phase {
+            return None;
+        }
+
+        self.current_phase = phase;
+        self.next_ability_idx = 0;
+
+        let phase = &self.phases[phase.unwrap()];
+        self.next_ability = elapsed_time + phase.cooldown;
+        Some(phase)
+    }
+
+    /// the next ability to cast from the current phase, if one is due
+    pub fn next_cast(&mut self, elapsed_time: f32) -> Option<u16> {
+        let phase = match self.current_phase {
+            None => return None,
+            Some(idx) => &self.phases[idx],
+        };
+
+        if phase.abilities.is_empty() || self.next_ability > elapsed_time {
+            return None;
+        }
+
+        let ability = phase.abilities[self.next_ability_idx % phase.abilities.len()];
+        self.next_ability_idx += 1;
+        self.next_ability = elapsed_time + phase.cooldown;
+        Some(ability)
+    }
+}
+
+/// adds the components for an enemy's behaviours to the entity being spawned
+pub fn with_behaviours<'a>(
+    commands: &'a mut Commands,
+    definition: &EnemyDefinition,
+) -> &'a mut Commands {
+    for behaviour in definition.behaviours.iter() {
+        match *behaviour {
+            EnemyBehaviour::Ranged => {}
+            EnemyBehaviour::Charger {
+                speed,
+                health_threshold,
+            } => {
+                commands.with(Charger {
+                    speed,
+                    health_threshold,
+                    charge_until: 0.,
+                });
+            }
+            EnemyBehaviour::Support {
+                heal,
+                armour,
+                radius,
+                cooldown,
+            } => {
+                commands.with(Support {
+                    heal,
+                    armour,
+                    radius,
+                    cooldown,
+                    next_pulse: 0.,
+                });
+            }
+            EnemyBehaviour::LaneSwitcher { interval } => {
+                commands.with(LaneSwitcher {
+                    interval,
+                    next_check: 0.,
+                });
+            }
+        };
+    }
+
+    if !definition.boss_phases.is_empty() {
+        commands.with(Boss::new(definition.boss_phases.clone()));
+    }
+
+    commands
+}
+
+/// the nearest lane without any heroes in it, if the given lane has heroes
+pub fn undefended_lane(lane: usize, lane_count: usize, hero_lanes: &[usize]) -> Option<usize> {
+    if !hero_lanes.contains(&lane) {
+        return None;
+    }
+
+    (0..lane_count)
+        .filter(|other| !hero_lanes.contains(other))
+        .min_by_key(|other| (*other as isize - lane as isize).abs())
+}
+
+/// heals an enemy up to its max health, returning how much health was actually restored.
+/// Dead enemies have already paid out their xp, so they can't be healed
+fn apply_heal(health: &mut Health, amount: f32) -> f32 {
+    if health.target_health <= 0. {
+        return 0.;
+    }
+
+    let healed = ((health.target_health + amount).min(health.max_health.value)
+        - health.target_health)
+        .max(0.);
+    health.target_health += healed;
+    healed
+}
+
+/// enemies don't have `Stats`, so their buffs are expired here instead
+fn enemy_buff_system(
+    game_time: Res<GameTime>,
+    _enemy: &Enemy,
+    mut movement: Mut<Movement>,
+    mut defence: Mut<Defence>,
+    mut health: Mut<Health>,
+) {
+    movement.movement_speed.update(game_time.elapsed_time);
+    defence.base_armour.update(game_time.elapsed_time);
+    health.max_health.update(game_time.elapsed_time);
+    health.regeneration.update(game_time.elapsed_time);
+}
+
+fn charger_system(
+    game_time: Res<GameTime>,
+    mut chargers: Query<(
+        &Enemy,
+        &mut Charger,
+        &mut Movement,
+        &mut ThreatTable<ThreatSource>,
+    )>,
+    mut players: Query<Without<Incapacitated, (Entity, &Player, &Health)>>,
+) {
+    // (entity, lane, health fraction)
+    let heroes: Vec<(Entity, usize, f32)> = players
+        .iter()
+        .iter()
+        .map(|(e, player, health)| {
+            (
+                e,
+                player.current_lane,
+                health.current_health / health.max_health.value.max(1.),
+            )
+        })
+        .collect();
+
+    for (enemy, mut charger, mut movement, mut threat) in &mut chargers.iter() {
+        if charger.charge_until > game_time.elapsed_time {
+            continue;
+        }
+
+        let target = heroes
+            .iter()
+            .filter(|(_, lane, fraction)| {
+                *lane == enemy.lane && *fraction < charger.health_threshold
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+
+        match target {
+            None => {}
+            Some((hero, _, _)) => {
+                charger.charge_until = game_time.elapsed_time + CHARGE_DURATION;
+                movement.movement_speed.add_buff(Buff {
+                    expiry: charger.charge_until,
+                    percentage: charger.speed - 1.,
+                    amount: 0.,
+                });
+                threat.add(ThreatSource::Hero(*hero), CHARGE_THREAT);
+            }
+        };
+    }
+}
+
+fn support_system(
+    game_time: Res<GameTime>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut vfx_events: ResMut<Events<SpawnVfxEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut supporters: Query<(Entity, &Enemy, &mut Support, &Transform)>,
+    mut enemies: Query<(Entity, &Enemy, &mut Health, &mut Defence, &Transform)>,
+) {
+    // (supporter, position, heal, armour, radius, expiry)
+    let mut pulses: Vec<(Entity, Vec3, f32, f32, f32, f32)> = vec![];
+    for (entity, _, mut support, tx) in &mut supporters.iter() {
+        if support.next_pulse > game_time.elapsed_time {
+            continue;
+        }
+
+        support.next_pulse = game_time.elapsed_time + support.cooldown;
+        pulses.push((
+            entity,
+            tx.translation(),
+            support.heal,
+            support.armour,
+            support.radius,
+            support.next_pulse,
+        ));
+    }
+
+    for (supporter, position, heal, armour, radius, expiry) in pulses.into_iter() {
+        vfx_events.send(SpawnVfxEvent::at("heal", position));
+
+        for (entity, _, mut health, mut defence, tx) in &mut enemies.iter() {
+            if entity == supporter
+                || health.target_health <= 0.
+                || (tx.translation() - position).length() > radius
+            {
+                continue;
+            }
+
+            defence.base_armour.add_buff(Buff {
+                expiry,
+                percentage: 0.,
+                amount: armour,
+            });
+
+            let healed = apply_heal(&mut health, heal);
+            if healed <= 0. {
+                continue;
+            }
+
+            text_events.send(FloatingTextEvent::heal(healed, tx.translation()));
+            combat_events.send(CombatEvent::heal(
+                game_time.elapsed_time,
+                Combatant::Enemy(supporter),
+                Combatant::Enemy(entity),
+                None,
+                healed,
+            ));
+        }
+    }
+}
+
+fn lane_switch_system(
+    game_time: Res<GameTime>,
+    map: Res<MapDefinition>,
+    mut switchers: Query<(
+        &mut Enemy,
+        &Transform,
+        &mut LaneSwitcher,
+        &mut AttackTarget,
+        &mut BaseAttack,
+    )>,
+    mut players: Query<Without<Incapacitated, &Player>>,
+) {
+    let hero_lanes: Vec<usize> = players
+        .iter()
+        .iter()
+        .map(|player| player.current_lane)
+        .collect();
+
+    for (mut enemy, transform, mut switcher, mut target, mut attack) in &mut switchers.iter() {
+        if switcher.next_check > game_time.elapsed_time {
+            continue;
+        }
+
+        switcher.next_check = game_time.elapsed_time + switcher.interval;
+
+        let lane = match undefended_lane(enemy.lane, map.lane_count(), &hero_lanes) {
+            None => continue,
+            Some(lane) => lane,
+        };
+
+        println!("Enemy switching from lane {} to {}", enemy.lane, lane);
+
+        // head for the nearest part of the new lane's path
+        let position = transform.translation().truncate() - enemy.offset;
+        enemy.lane = lane;
+        enemy.waypoint = map.lanes[lane].next_waypoint(position);
+
+        attack.interrupt();
+        target.entity = None;
+        target.is_obelisk = false;
+    }
+}
+
+/// A boss ability waiting to be applied
+struct BossCast {
+    boss: Entity,
+    lane: usize,
+    position: Vec3,
+    range: f32,
+    ability_id: u16,
+}
+
+fn boss_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    abilities: Res<AbilityDatabase>,
+    calculator: Res<CombatCalculator>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut vfx_events: ResMut<Events<SpawnVfxEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut bosses: Query<(Entity, &Enemy, &mut Boss, &Health, &BaseAttack, &Transform)>,
+    mut enemies: Query<(
+        Entity,
+        &Enemy,
+        &mut Health,
+        &mut Defence,
+        &mut Movement,
+        &Transform,
+    )>,
+    mut players: Query<
+        Without<Incapacitated, (Entity, &Player, &mut Health, &Defence, &Transform)>,
+    >,
+) {
+    let mut casts: Vec<BossCast> = vec![];
+    for (entity, enemy, mut boss, health, attack, tx) in &mut bosses.iter() {
+        let fraction = health.target_health / health.max_health.value.max(1.);
+        match boss.update_phase(fraction, game_time.elapsed_time) {
+            Some(BossPhase {
+                name: Some(name), ..
+            }) => {
+                text_events.send(FloatingTextEvent::status(name, tx.translation()));
+            }
+            _ => {}
+        };
+
+        match boss.next_cast(game_time.elapsed_time) {
+            None => {}
+            Some(ability_id) => casts.push(BossCast {
+                boss: entity,
+                lane: enemy.lane,
+                position: tx.translation(),
+                range: attack.attack_range,
+                ability_id,
+            }),
+        };
+    }
+
+    for cast in casts.into_iter() {
+        let definition = match abilities.abilities.get(&cast.ability_id) {
+            None => {
+                println!("Unknown boss ability {}, skipping", cast.ability_id);
+                continue;
+            }
+            Some(definition) => definition,
+        };
+
+        println!("Boss casting {}", definition.name);
+
+        for effect in definition.effects.iter() {
+            match effect {
+                AbilityDetail::Attack(_) | AbilityDetail::AttackArea(_, _) => {
+                    // single attacks hit the nearest hero within the boss's attack range,
+                    // area attacks hit every hero within the area
+                    let (data, range, is_area) = match effect {
+                        AbilityDetail::AttackArea(data, range) => (data, *range as f32, true),
+                        AbilityDetail::Attack(data) => (data, cast.range, false),
+                        _ => {
+                            println!(
+                                "Boss ability {} has an unexpected attack effect, skipping",
+                                cast.ability_id
+                            );
+                            continue;
+                        }
+                    };
+
+                    let mut in_range: Vec<(f32, u8)> = players
+                        .iter()
+                        .iter()
+                        .filter(|(_, player, _, _, _)| player.current_lane == cast.lane)
+                        .map(|(_, player, _, _, tx)| {
+                            (
+                                (tx.translation() - cast.position).length(),
+                                player.player_id,
+                            )
+                        })
+                        .filter(|(distance, _)| *distance <= range)
+                        .collect();
+                    in_range.sort_by(|a, b| a.0.total_cmp(&b.0));
+                    if !is_area {
+                        in_range.truncate(1);
+                    }
+
+                    for (_, player, mut health, defence, tx) in &mut players.iter() {
+                        if !in_range.iter().any(|(_, id)| *id == player.player_id) {
+                            continue;
+                        }
+
+                        let result = calculator.resolve(
+                            &BaseAttack {
+                                min_attack_damage: data.min_damage,
+                                max_attack_damage: data.max_damage,
+                                damage_type: data.damage_type,
+                                ..Default::default()
+                            },
+                            defence,
+                        );
+                        let was_alive = health.target_health > 0.;
+                        health.target_health -= result.damage as f32;
+                        text_events.send(FloatingTextEvent::damage(&result, tx.translation()));
+                        combat_events.send(CombatEvent::damage(
+                            game_time.elapsed_time,
+                            Combatant::Enemy(cast.boss),
+                            Combatant::Hero(player.player_id),
+                            Some(cast.ability_id),
+                            &result,
+                            was_alive && health.target_health <= 0.,
+                        ));
+                    }
+                }
+                AbilityDetail::Heal(data) => {
+                    for (entity, enemy, mut health, _, _, tx) in &mut enemies.iter() {
+                        if enemy.lane != cast.lane {
+                            continue;
+                        }
+
+                        let healed = apply_heal(&mut health, data.burst_heal);
+                        if healed <= 0. {
+                            continue;
+                        }
+
+                        text_events.send(FloatingTextEvent::heal(healed, tx.translation()));
+                        combat_events.send(CombatEvent::heal(
+                            game_time.elapsed_time,
+                            Combatant::Enemy(cast.boss),
+                            Combatant::Enemy(entity),
+                            Some(cast.ability_id),
+                            healed,
+                        ));
+                    }
+                }
+                AbilityDetail::Buff(detail) => {
+                    // buffs only apply to the boss itself, and always wear off so repeated
+                    // casts don't stack forever
+                    let buff = Buff {
+                        expiry: game_time.elapsed_time
+                            + detail.duration.unwrap_or(BOSS_BUFF_DURATION),
+                        ..detail.buff
+                    };
+
+                    match detail.buff_type {
+                        BuffType::Armour => match enemies.get_mut::<Defence>(cast.boss) {
+                            Err(_) => {}
+                            Ok(mut defence) => defence.base_armour.add_buff(buff),
+                        },
+                        BuffType::Health => match enemies.get_mut::<Health>(cast.boss) {
+                            Err(_) => {}
+                            Ok(mut health) => health.max_health.add_buff(buff),
+                        },
+                        BuffType::Regeneration => match enemies.get_mut::<Health>(cast.boss) {
+                            Err(_) => {}
+                            Ok(mut health) => health.regeneration.add_buff(buff),
+                        },
+                        BuffType::MovementSpeed => match enemies.get_mut::<Movement>(cast.boss) {
+                            Err(_) => {}
+                            Ok(mut movement) => movement.movement_speed.add_buff(buff),
+                        },
+                        BuffType::Mana => {}
+                    };
+                }
+                AbilityDetail::SpawnEffect(name) => {
+                    vfx_events.send(SpawnVfxEvent::at(name, cast.position));
+                }
+                AbilityDetail::Stun(duration) => {
+                    for (entity, player, _, _, tx) in &mut players.iter() {
+                        if player.current_lane != cast.lane
+                            || (tx.translation() - cast.position).length() > cast.range
+                        {
+                            continue;
+                        }
+
+                        // the stun system interrupts the hero's swing
+                        commands.insert_one(
+                            entity,
+                            Stunned {
+                                end_time: game_time.elapsed_time + duration,
+                            },
+                        );
+                        text_events.send(FloatingTextEvent::status("Stunned!", tx.translation()));
+                    }
+                }
+                AbilityDetail::Revive(_) | AbilityDetail::Taunt(_) => {}
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phase(health_fraction: f32, abilities: Vec<u16>) -> BossPhase {
+        BossPhase {
+            name: None,
+            health_fraction,
+            abilities,
+            cooldown: 5.,
+        }
+    }
+
+    #[test]
+    fn bosses_move_through_phases_as_they_lose_health() {
+        let mut boss = Boss::new(vec![phase(0.5, vec![2, 3]), phase(1., vec![1])]);
+
+        assert!(boss.update_phase(1., 0.).is_some());
+        assert_eq!(boss.next_cast(1.), None);
+        assert_eq!(boss.next_cast(5.), Some(1));
+        assert_eq!(boss.next_cast(6.), None);
+
+        // skipping straight past a phase still ends up in the last one reached
+        assert!(boss.update_phase(0.4, 6.).is_some());
+        assert!(boss.update_phase(0.3, 7.).is_none());
+        assert_eq!(boss.next_cast(11.), Some(2));
+        assert_eq!(boss.next_cast(16.), Some(3));
+        assert_eq!(boss.next_cast(21.), Some(2));
+    }
+
+    #[test]
+    fn only_heals_living_enemies_up_to_max_health() {
+        let mut health = Health::new(100., 0.);
+        health.target_health = 90.;
+        assert_eq!(apply_heal(&mut health, 40.), 10.);
+        assert_eq!(health.target_health, 100.);
+
+        health.target_health = 0.;
+        assert_eq!(apply_heal(&mut health, 40.), 0.);
+        assert_eq!(health.target_health, 0.);
+    }
+
+    #[test]
+    fn switches_to_the_nearest_undefended_lane() {
+        assert_eq!(undefended_lane(0, 3, &[1, 2]), None);
+        assert_eq!(undefended_lane(0, 3, &[0, 2]), Some(1));
+        assert_eq!(undefended_lane(2, 3, &[2, 1]), Some(0));
+        assert_eq!(undefended_lane(1, 3, &[0, 1, 2]), None);
+        assert_eq!(undefended_lane(1, 5, &[0, 1, 2]), Some(3));
+    }
+}
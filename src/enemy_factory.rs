@@ -1,53 +1,281 @@
-/// Do this from data files in a real game
-use crate::{components::*, constants::MELEE_RANGE};
-use spectre_core::{BuffableStatistic, Health};
+/// Loads in the enemy definitions from the RON files in `assets/data`. Enemies are looked up
+/// by the id used in the wave files, i.e. "wolf"
+use crate::{
+    abilities::DamageType,
+    components::*,
+    constants::{MELEE_RANGE, PLAYER_OFFSET_Y},
+    projectiles::ProjectileSpec,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_animations::prelude::AnimationClip;
+use spectre_combat::{damage::Resistances, threat::ThreatTable};
+use spectre_core::{BuffableStatistic, Health, Movement};
+use spectre_loaders::data_loaders::MergeData;
+use std::collections::HashMap;
 
-pub enum EnemyType {
-    Wolf,
-    Bear,
+/// The enemy files to load, merged in this order
+pub const ENEMY_DATA_FILES: [&str; 1] = ["assets/data/enemies.enr"];
+
+/// Tags which change how an enemy behaves, see `enemy_behaviours`
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum EnemyBehaviour {
+    /// stops short of the heroes so it can attack from range
+    Ranged,
+    /// sprints at heroes in its lane once their health drops below the threshold,
+    /// i.e. 0.3 for 30% health. The speed is a multiplier
+    Charger { speed: f32, health_threshold: f32 },
+    /// every `cooldown` seconds heals nearby enemies and adds to their armour until
+    /// the next pulse
+    Support {
+        heal: f32,
+        armour: f32,
+        radius: f32,
+        cooldown: f32,
+    },
+    /// every `interval` seconds checks for heroes in its lane, and moves to the nearest
+    /// undefended lane if there are any
+    LaneSwitcher { interval: f32 },
+}
+
+/// A phase of a boss fight
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BossPhase {
+    /// shown over the boss when the phase starts
+    #[serde(default)]
+    pub name: Option<String>,
+
+    /// the phase starts once the boss's health drops to this fraction of its max health
+    pub health_fraction: f32,
+
+    /// ids from the ability database, cast in order
+    pub abilities: Vec<u16>,
+
+    /// seconds between each ability
+    pub cooldown: f32,
+}
+
+fn default_cost() -> usize {
+    1
+}
+
+/// How an enemy or hero attacks
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct AttackDefinition {
+    /// how far away the attacker can reach, None for melee
+    #[serde(default)]
+    pub range: Option<f32>,
+
+    /// seconds between each attack
+    pub speed: f32,
+    pub min_damage: i32,
+    pub max_damage: i32,
+
+    #[serde(default)]
+    pub damage_type: DamageType,
+
+    #[serde(default)]
+    pub crit_chance: f32,
+
+    /// the fraction of each attack spent winding up before the impact
+    #[serde(default)]
+    pub wind_up: f32,
+
+    #[serde(default)]
+    pub projectile: Option<ProjectileSpec>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DefenceDefinition {
+    #[serde(default)]
+    pub armour: f32,
+    #[serde(default)]
+    pub fire_armour: i32,
+    #[serde(default)]
+    pub electricity_armour: i32,
+    #[serde(default)]
+    pub poison_armour: i32,
+    #[serde(default)]
+    pub frost_armour: i32,
+    #[serde(default)]
+    pub resistances: Resistances,
+    #[serde(default)]
+    pub dodge_chance: f32,
+    #[serde(default)]
+    pub block_chance: f32,
+}
+
+/// Describes a type of enemy
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EnemyDefinition {
+    pub health: f32,
+
+    #[serde(default)]
+    pub health_regen: f32,
+
+    pub movement_speed: f32,
+    pub xp_reward: usize,
+
+    /// how much of a generated wave's difficulty budget this enemy uses
+    #[serde(default = "default_cost")]
+    pub cost: usize,
+
+    pub attack: AttackDefinition,
+
+    #[serde(default)]
+    pub defence: DefenceDefinition,
+
+    /// the Aseprite sheet to use, i.e. "enemy_wolf" for "assets/enemy_wolf.json"
+    pub sheet: String,
+
+    /// the clips from the sheet the enemy uses, the first is played when it spawns.
+    /// Every clip in the sheet is used if this is empty
+    #[serde(default)]
+    pub clips: Vec<String>,
+
+    #[serde(default)]
+    pub behaviours: Vec<EnemyBehaviour>,
+
+    /// enemies with phases are bosses, which cast abilities as they lose health
+    #[serde(default)]
+    pub boss_phases: Vec<BossPhase>,
 }
 
-pub fn get_enemy_bundle(enemy_type: EnemyType, lane: usize) -> EnemyBundle {
-    match enemy_type {
-        EnemyType::Wolf => get_wolf(lane),
-        EnemyType::Bear => get_bear(lane),
+impl EnemyDefinition {
+    pub fn has_behaviour(&self, behaviour: EnemyBehaviour) -> bool {
+        self.behaviours.contains(&behaviour)
+    }
+
+    pub fn attack_range(&self) -> f32 {
+        self.attack.range.unwrap_or(MELEE_RANGE)
+    }
+
+    /// picks the clips this enemy uses out of those imported from its sheet
+    pub fn select_clips(&self, sheet_clips: Vec<AnimationClip>) -> Vec<AnimationClip> {
+        if self.clips.is_empty() {
+            return sheet_clips;
+        }
+
+        let selected: Vec<AnimationClip> = self
+            .clips
+            .iter()
+            .filter_map(|name| sheet_clips.iter().find(|clip| clip.name == *name).cloned())
+            .collect();
+
+        if selected.is_empty() {
+            println!(
+                "None of the clips {:?} are in sheet {}, using every clip",
+                self.clips, self.sheet
+            );
+            return sheet_clips;
+        }
+
+        selected
     }
 }
 
-fn get_wolf(lane: usize) -> EnemyBundle {
-    EnemyBundle {
-        enemy: Enemy::new(lane, 35),
-        health: Health::new(30., 0.),
-        attack: BaseAttack {
-            attack_range: MELEE_RANGE,
-            attack_speed: BuffableStatistic::new(1.),
-            min_attack_damage: 3,
-            max_attack_damage: 5,
-            ..Default::default()
-        },
-        defence: Defence {
-            base_armour: BuffableStatistic::new(0.),
-            ..Default::default()
-        },
-        attack_target: AttackTarget::default(),
+/// A resource containing every enemy that can be spawned, keyed by id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct EnemyDatabase {
+    pub enemies: HashMap<String, EnemyDefinition>,
+}
+
+impl EnemyDatabase {
+    pub fn get(&self, id: &str) -> Option<&EnemyDefinition> {
+        self.enemies.get(id)
     }
 }
 
-fn get_bear(lane: usize) -> EnemyBundle {
+impl MergeData for EnemyDatabase {
+    fn merge(&mut self, other: &Self) {
+        for (id, enemy) in other.enemies.iter() {
+            if self.enemies.contains_key(id) {
+                println!("Duplicate enemy id {} found, replacing", id);
+            }
+
+            self.enemies.insert(id.clone(), enemy.clone());
+        }
+    }
+}
+
+/// builds an enemy which walks down the lane's path
+pub fn get_enemy_bundle(definition: &EnemyDefinition, lane: usize) -> EnemyBundle {
+    let mut enemy = Enemy::new(lane, definition.xp_reward);
+
+    // stop a little inside range rather than walking up to the heroes
+    if definition.has_behaviour(EnemyBehaviour::Ranged) {
+        enemy.stand_off = PLAYER_OFFSET_Y + 0.8 * definition.attack_range();
+    }
+
+    let attack = &definition.attack;
+    let defence = &definition.defence;
+
     EnemyBundle {
-        enemy: Enemy::new(lane, 50),
-        health: Health::new(50., 0.1),
+        enemy,
+        health: Health::new(definition.health, definition.health_regen),
+        movement: Movement {
+            movement_speed: BuffableStatistic::new(definition.movement_speed),
+        },
         attack: BaseAttack {
-            attack_range: MELEE_RANGE,
-            attack_speed: BuffableStatistic::new(0.95),
-            min_attack_damage: 9,
-            max_attack_damage: 12,
+            attack_range: definition.attack_range(),
+            attack_speed: BuffableStatistic::new(attack.speed),
+            min_attack_damage: attack.min_damage,
+            max_attack_damage: attack.max_damage,
+            damage_type: attack.damage_type,
+            crit_chance: attack.crit_chance,
+            projectile: attack.projectile.clone(),
+            wind_up: attack.wind_up,
             ..Default::default()
         },
         defence: Defence {
-            base_armour: BuffableStatistic::new(1.),
-            ..Default::default()
+            base_armour: BuffableStatistic::new(defence.armour),
+            fire_armour: defence.fire_armour,
+            electricity_armour: defence.electricity_armour,
+            poison_armour: defence.poison_armour,
+            frost_armour: defence.frost_armour,
+            resistances: defence.resistances.clone(),
+            dodge_chance: defence.dodge_chance,
+            block_chance: defence.block_chance,
         },
         attack_target: AttackTarget::default(),
+        threat: ThreatTable::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectre_loaders::data_loaders::load_merged;
+
+    #[test]
+    fn loads_the_enemy_files() {
+        let database = load_merged::<EnemyDatabase>(&ENEMY_DATA_FILES);
+
+        let mut ids = database.enemies.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        assert_eq!(
+            ids,
+            vec![
+                "bear",
+                "troll",
+                "troll_king",
+                "troll_shaman",
+                "wolf",
+                "wolf_runner"
+            ]
+        );
+
+        // spot check the values carried over from the old hand written enemies
+        let wolf = get_enemy_bundle(database.get("wolf").unwrap(), 1);
+        assert_eq!(wolf.enemy.xp_reward, 35);
+        assert_eq!(wolf.attack.attack_range, MELEE_RANGE);
+        assert_eq!(wolf.defence.resistances.apply(DamageType::Fire, 10), 15);
+
+        let troll = database.get("troll").unwrap();
+        assert!(troll.has_behaviour(EnemyBehaviour::Ranged));
+        assert!(troll.attack.projectile.is_some());
+
+        let bundle = get_enemy_bundle(troll, 1);
+        assert!(bundle.enemy.stand_off > wolf.enemy.stand_off);
     }
 }
@@ -5,7 +5,7 @@ use spectre_time::GameSpeedRequest;
 
 use crate::{
     components::CurrentWave, components::Enemy, components::ObeliskStatusImageUiLink,
-    game_scenes::MyGameScenes, waves::WAVE_DATA,
+    endless::GameMode, game_scenes::MyGameScenes, waves::SpawnQueue, waves::WaveDatabase,
 };
 
 pub struct WaveSpawnedEvent {
@@ -38,33 +38,51 @@ pub fn end_of_day_system(
     mut state: ResMut<EndOfDayEventListener>,
     mut game_state: ResMut<GameState<MyGameScenes>>,
     waves: Res<CurrentWave>,
+    wave_data: Res<WaveDatabase>,
+    mut mode: ResMut<GameMode>,
+    mut spawn_queue: ResMut<SpawnQueue>,
     events: Res<Events<EndOfDayEvent>>,
     audio: Res<AudioOutput>,
     assets: Res<MaterialsAndTextures>,
     mut enemies: Query<With<Enemy, Entity>>,
 ) {
-    let mut found = false;
-    for _ in state.end_of_day_reader.iter(&events) {
+    let mut survived = None;
+    for event in state.end_of_day_reader.iter(&events) {
         println!("End of day - pausing game");
-        found = true;
+        survived = Some(event.0);
         break;
     }
 
     // no events, just return
-    if !found {
-        return;
-    }
+    let survived = match survived {
+        None => return,
+        Some(survived) => survived,
+    };
 
     audio.play(assets.leaving_audio);
 
+    // nothing else from this wave should spawn
+    spawn_queue.clear();
+
     // pause the game
     commands.spawn((GameSpeedRequest {
         new_game_speed: 0.0,
     },));
 
+    // endless runs carry on until the obelisk falls
+    let game_over = match &mut *mode {
+        GameMode::Story => waves.wave_idx >= wave_data.waves.len(),
+        GameMode::Endless(run) => {
+            if survived {
+                run.next_day();
+            }
+            !survived
+        }
+    };
+
     // show the ability UI
-    if waves.wave_idx >= WAVE_DATA.len() {
-        println!("Showing victory UI");
+    if game_over {
+        println!("Showing game over UI");
         game_state.set_transition(MyGameScenes::GameOver);
     } else {
         // destroying remaining enemies
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+use bevy_easings::EaseFunction;
+use interpolation::Ease;
+use spectre_combat::damage::CombatResult;
+use spectre_time::GameTime;
+
+use crate::{
+    assets::MaterialsAndTextures,
+    components::GameSceneEntity,
+    constants::{RESOLUTION_X, RESOLUTION_Y},
+};
+
+/// How far floating text rises over its lifetime, in pixels
+const FLOATING_TEXT_RISE: f32 = 40.;
+
+/// How long floating text is shown for, in seconds of game time
+const FLOATING_TEXT_DURATION: f32 = 1.2;
+
+/// Shows damage numbers, heals and status changes rising up from characters
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<FloatingTextEvent>()
+            .init_resource::<FloatingTextEventListener>()
+            .add_system(spawn_floating_text_system.system())
+            .add_system(floating_text_system.system());
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FloatingTextKind {
+    Damage,
+    Crit,
+    Heal,
+    Status,
+}
+
+impl FloatingTextKind {
+    fn color(&self) -> Color {
+        match self {
+            FloatingTextKind::Damage => Color::rgb(0.9, 0.9, 0.9),
+            FloatingTextKind::Crit => Color::rgb(1., 0.75, 0.1),
+            FloatingTextKind::Heal => Color::rgb(0.3, 0.9, 0.3),
+            FloatingTextKind::Status => Color::rgb(0.9, 0.2, 0.2),
+        }
+    }
+
+    fn font_size(&self) -> f32 {
+        match self {
+            FloatingTextKind::Crit => 24.,
+            _ => 16.,
+        }
+    }
+}
+
+/// Send to show some text rising up from a position in the world
+pub struct FloatingTextEvent {
+    pub text: String,
+    pub kind: FloatingTextKind,
+    pub position: Vec3,
+}
+
+impl FloatingTextEvent {
+    /// a damage number, highlighted if the hit was a critical
+    pub fn damage(result: &CombatResult, position: Vec3) -> Self {
+        if result.is_dodged {
+            FloatingTextEvent {
+                text: "Dodged".to_string(),
+                kind: FloatingTextKind::Damage,
+                position,
+            }
+        } else if result.is_crit {
+            FloatingTextEvent {
+                text: format!("{}!", result.damage),
+                kind: FloatingTextKind::Crit,
+                position,
+            }
+        } else {
+            FloatingTextEvent {
+                text: format!("{}", result.damage),
+                kind: FloatingTextKind::Damage,
+                position,
+            }
+        }
+    }
+
+    pub fn heal(amount: f32, position: Vec3) -> Self {
+        FloatingTextEvent {
+            text: format!("+{}", amount.round() as i32),
+            kind: FloatingTextKind::Heal,
+            position,
+        }
+    }
+
+    pub fn status(text: &str, position: Vec3) -> Self {
+        FloatingTextEvent {
+            text: text.to_string(),
+            kind: FloatingTextKind::Status,
+            position,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct FloatingTextEventListener {
+    pub floating_text_reader: EventReader<FloatingTextEvent>,
+}
+
+/// Text which rises and fades out, then despawns
+pub struct FloatingText {
+    pub started_at: f32,
+    pub duration: f32,
+
+    /// the screen position the text started at
+    pub start: Vec2,
+    pub color: Color,
+}
+
+impl FloatingText {
+    /// the progress through the animation, from 0 to 1
+    fn progress(&self, elapsed_time: f32) -> f32 {
+        ((elapsed_time - self.started_at) / self.duration)
+            .max(0.)
+            .min(1.)
+    }
+
+    /// the (rise, alpha) at the given time. Eased by hand rather than with the bevy_easings
+    /// components, as they run off real time and wouldn't pause with the game
+    fn eased(&self, elapsed_time: f32) -> (f32, f32) {
+        let progress = self.progress(elapsed_time);
+        let rise = FLOATING_TEXT_RISE * progress.calc(EaseFunction::QuadraticOut);
+        let alpha = 1. - progress.calc(EaseFunction::QuadraticIn);
+
+        (rise, alpha)
+    }
+}
+
+fn spawn_floating_text_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    assets: Res<MaterialsAndTextures>,
+    events: Res<Events<FloatingTextEvent>>,
+    mut listener: ResMut<FloatingTextEventListener>,
+) {
+    for event in listener.floating_text_reader.iter(&events) {
+        // the UI is positioned from the bottom left, the camera is centered on the origin
+        let start = Vec2::new(
+            event.position.x() + RESOLUTION_X as f32 / 2.,
+            event.position.y() + RESOLUTION_Y as f32 / 2.,
+        );
+        let color = event.kind.color();
+
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Px(start.x()),
+                        bottom: Val::Px(start.y()),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text {
+                    value: event.text.clone(),
+                    font: assets.main_font,
+                    style: TextStyle {
+                        font_size: event.kind.font_size(),
+                        color,
+                    },
+                },
+                ..Default::default()
+            })
+            .with(FloatingText {
+                started_at: game_time.elapsed_time,
+                duration: FLOATING_TEXT_DURATION,
+                start,
+                color,
+            })
+            .with(GameSceneEntity);
+    }
+}
+
+fn floating_text_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    mut query: Query<(Entity, &FloatingText, &mut Style, &mut Text)>,
+) {
+    for (entity, floating, mut style, mut text) in &mut query.iter() {
+        if floating.progress(game_time.elapsed_time) >= 1. {
+            commands.despawn(entity);
+            continue;
+        }
+
+        let (rise, alpha) = floating.eased(game_time.elapsed_time);
+        style.position.bottom = Val::Px(floating.start.y() + rise);
+
+        let mut color = floating.color;
+        color.a = alpha;
+        text.style.color = color;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rises_and_fades_over_its_lifetime() {
+        let text = FloatingText {
+            started_at: 10.,
+            duration: 1.,
+            start: Vec2::new(0., 0.),
+            color: Color::WHITE,
+        };
+
+        assert_eq!(text.eased(10.), (0., 1.));
+
+        let (rise, alpha) = text.eased(10.5);
+        assert!(rise > FLOATING_TEXT_RISE / 2.);
+        assert!(alpha > 0.5);
+
+        assert_eq!(text.eased(12.), (FLOATING_TEXT_RISE, 0.));
+    }
+}
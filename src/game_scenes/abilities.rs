@@ -1,11 +1,16 @@
 use crate::{
-    abilities::AbilityDefinition, assets::MaterialsAndTextures, combat::resolve_combat,
-    constants::FLAME_WALL_ID, constants::HEAL_ID, constants::MELEE_RANGE,
-    constants::TARGET_LOCATIONS,
+    abilities::AbilityDefinition, assets::MaterialsAndTextures, constants::GAME_ELEMENT_LAYER,
+    constants::MELEE_RANGE, map::MapDefinition,
+};
+use crate::{
+    abilities::AbilityDetail,
+    combat_log::{CombatEvent, Combatant},
+    floating_text::FloatingTextEvent,
+    player_ui::text,
 };
-use crate::{abilities::AbilityDetail, player_ui::text};
 use bevy::prelude::*;
-use spectre_animations::spawn_animated_spritesheet;
+use spectre_animations::prelude::SpawnVfxEvent;
+use spectre_combat::damage::CombatCalculator;
 use spectre_core::Health;
 use spectre_state::GameState;
 use spectre_state::GameStatus;
@@ -13,7 +18,7 @@ use spectre_time::GameTime;
 
 use crate::{
     abilities::ability_data::AbilityDatabase, abilities::AbilityPurchaseRequest, components::*,
-    events::*, game_scenes::MyGameScenes,
+    events::*, game_scenes::MyGameScenes, hero_classes::HeroClass,
 };
 
 pub struct AbilityGuiMarker;
@@ -27,7 +32,7 @@ pub fn setup_ability_scene(
     mut ability_data: ResMut<AbilityDatabase>,
     mut game_running_components: Query<(Entity, &GameRunningPlayerUi)>,
     mut sidebar_components: Query<(Entity, &MainGameSidebarUi)>,
-    mut player_query: Query<(&Player, &PlayerAbilityActions)>,
+    mut player_query: Query<(&Player, &HeroClass, &PlayerAbilityActions)>,
 ) {
     if !game_state.is_in_scene(&MyGameScenes::Abilities)
         || !game_state.is_in_status(&GameStatus::Entering)
@@ -59,20 +64,25 @@ fn spawn_ability_sidebar(
     mut commands: &mut Commands,
     player_score: &Res<PlayerScore>,
     assets: &Res<MaterialsAndTextures>,
-    player_query: &mut Query<(&Player, &PlayerAbilityActions)>,
+    player_query: &mut Query<(&Player, &HeroClass, &PlayerAbilityActions)>,
     mut ability_data: &mut ResMut<AbilityDatabase>,
 ) {
-    let mut player_uis: Vec<Entity> = Vec::default();
-    for (player, actions) in &mut player_query.iter() {
-        player_uis.push(spawn_player_ability_ui(
+    let mut player_uis: Vec<(u8, Entity)> = Vec::default();
+    for (player, class, actions) in &mut player_query.iter() {
+        let ui = spawn_player_ability_ui(
             &player,
+            &class,
             &actions,
             &mut commands,
             &assets,
             &mut ability_data,
-        ));
+        );
+        player_uis.push((player.player_id, ui));
     }
 
+    // the sidebar is a column, so add the players last first
+    player_uis.sort_by_key(|(player_id, _)| std::cmp::Reverse(*player_id));
+
     let button = commands
         .spawn(ButtonComponents {
             style: Style {
@@ -196,24 +206,16 @@ fn spawn_ability_sidebar(
         .current_entity()
         .unwrap();
 
-    commands.push_children(
-        parent,
-        &[
-            spacer.clone(),
-            abort_button,
-            spacer.clone(),
-            button,
-            player_uis[2],
-            player_uis[1],
-            player_uis[0],
-            available_xp,
-            heading,
-        ],
-    );
+    let mut children = vec![spacer.clone(), abort_button, spacer.clone(), button];
+    children.extend(player_uis.into_iter().map(|(_, ui)| ui));
+    children.push(available_xp);
+    children.push(heading);
+    commands.push_children(parent, &children);
 }
 
 pub fn spawn_player_ability_ui(
     player: &Player,
+    class: &HeroClass,
     actions: &PlayerAbilityActions,
     commands: &mut Commands,
     assets: &Res<MaterialsAndTextures>,
@@ -245,28 +247,32 @@ pub fn spawn_player_ability_ui(
                 ..Default::default()
             });
 
-            let mut to_spawn: Vec<AbilityDefinition> = vec![];
+            let mut to_spawn_ids: Vec<u16> = vec![];
 
             if actions.actions[0].action.is_none() {
-                to_spawn.push(
-                    ability_database
-                        .get(get_ability_id(player.player_id, 1))
-                        .clone(),
-                );
+                to_spawn_ids.push(class.ability_id(1));
             }
             if actions.actions[1].action.is_none() {
-                to_spawn.push(
-                    ability_database
-                        .get(get_ability_id(player.player_id, 2))
-                        .clone(),
-                );
+                to_spawn_ids.push(class.ability_id(2));
             }
 
             let next_level = player.get_next_level();
             if next_level.is_some() {
-                to_spawn.push(ability_database.get(next_level.unwrap() - 2).clone());
+                to_spawn_ids.push(next_level.unwrap() - 2);
             }
 
+            // classes from the data files may not have abilities for every slot
+            let to_spawn: Vec<AbilityDefinition> = to_spawn_ids
+                .iter()
+                .filter_map(|id| match ability_database.abilities.get(id) {
+                    None => {
+                        println!("Unknown ability {} for {}, not offering it", id, class.id);
+                        None
+                    }
+                    Some(ability) => Some(ability.clone()),
+                })
+                .collect();
+
             parent
                 .spawn(NodeComponents {
                     style: Style {
@@ -360,7 +366,7 @@ pub fn redraw_ability_ui_on_event(
     mut ability_data: ResMut<AbilityDatabase>,
     mut existing_sidebar_items: Query<(Entity, &AbilityGuiSidebarMarker)>,
     mut sidebar_components: Query<(Entity, &MainGameSidebarUi)>,
-    mut player_query: Query<(&Player, &PlayerAbilityActions)>,
+    mut player_query: Query<(&Player, &HeroClass, &PlayerAbilityActions)>,
 ) {
     let mut found = false;
 
@@ -392,55 +398,36 @@ pub fn redraw_ability_ui_on_event(
     }
 }
 
-fn get_ability_prefix(player_id: u8, slot_id: usize) -> String {
-    match player_id {
-        0 => match slot_id {
-            0 => "[q]".to_string(),
-            1 => "[w]".to_string(),
-            _ => "?".to_string(),
-        },
-        1 => match slot_id {
-            0 => "[e]".to_string(),
-            1 => "[r]".to_string(),
-            _ => "?".to_string(),
-        },
-        2 => match slot_id {
-            0 => "[d]".to_string(),
-            1 => "[f]".to_string(),
-            _ => "?".to_string(),
-        },
-        _ => "-".to_string(),
-    }
-}
-
-fn get_ability_id(player_id: u8, slot_id: usize) -> u16 {
-    (player_id as u16 + 1) * 1000 - 1 + slot_id as u16
-}
-
 pub fn ability_ui_updates(
     game_time: Res<GameTime>,
-    mut ability_database: ResMut<AbilityDatabase>,
+    ability_database: Res<AbilityDatabase>,
     mut abilities: Query<(&mut Text, &PlayerAbilityLink)>,
-    mut players: Query<(&Player, &PlayerAbilityActions)>,
+    mut players: Query<(&Player, &HeroClass, &PlayerAbilityActions)>,
 ) {
     for (mut text, link) in &mut abilities.iter() {
-        for (player, actions) in &mut players.iter() {
+        for (player, class, actions) in &mut players.iter() {
             if player.player_id != link.player_id {
                 continue;
             }
 
             let slot_action = actions.actions[link.action_number - 1];
-            let ability_data =
-                ability_database.get(get_ability_id(link.player_id, link.action_number));
-
-            if slot_action.action.is_none() {
-                text.value = "".to_string();
-                break;
-            }
+            let ability_data = match slot_action.action {
+                None => {
+                    text.value = "".to_string();
+                    break;
+                }
+                Some(id) => match ability_database.abilities.get(&id) {
+                    None => {
+                        text.value = "".to_string();
+                        break;
+                    }
+                    Some(ability) => ability,
+                },
+            };
 
             text.value = format!(
                 "{} {}",
-                get_ability_prefix(link.player_id, link.action_number - 1),
+                class.hotkey_prefix(link.action_number - 1),
                 if game_time.elapsed_time > slot_action.next_available {
                     ability_data.name.clone()
                 } else {
@@ -462,21 +449,17 @@ pub fn spawn_abilities(
     game_time: Res<GameTime>,
     input: ResMut<Input<KeyCode>>,
     mut database: ResMut<AbilityDatabase>,
-    mut players: Query<Without<Incapacitated, (&Player, &PlayerAbilityActions)>>,
+    mut players: Query<Without<Incapacitated, (&Player, &HeroClass, &PlayerAbilityActions)>>,
 ) {
-    for (player, abilities) in &mut players.iter() {
-        let id = player.player_id;
-
-        let codes: (KeyCode, KeyCode) = match id {
-            0 => (KeyCode::Q, KeyCode::W),
-            1 => (KeyCode::E, KeyCode::R),
-            2 => (KeyCode::D, KeyCode::F),
-            _ => (KeyCode::Q, KeyCode::W),
+    for (player, class, abilities) in &mut players.iter() {
+        let is_pressed = |slot_id: usize| match class.hotkey(slot_id) {
+            None => false,
+            Some(key) => input.just_pressed(key),
         };
 
-        let slot: Option<AbilityActionDetails> = if input.just_pressed(codes.0) {
+        let slot: Option<AbilityActionDetails> = if is_pressed(0) {
             Some(abilities.actions[0])
-        } else if input.just_pressed(codes.1) {
+        } else if is_pressed(1) {
             Some(abilities.actions[1])
         } else {
             None
@@ -505,6 +488,8 @@ pub fn spawn_abilities(
         println!("Spawned ability {}", definition.name);
         commands.spawn((SpawnedAbility {
             lane: player.current_lane,
+            player_id: player.player_id,
+            ability_id: definition.id,
             effects: definition.effects,
             applied: false,
         },));
@@ -515,11 +500,16 @@ pub fn spawn_abilities(
 /// a lot of this stuff (i.e. resolving combat) really shouldn't be done here - should raise an event or something instead?
 pub fn execute_abilities(
     mut commands: Commands,
-    assets: Res<MaterialsAndTextures>,
+    calculator: Res<CombatCalculator>,
+    map: Res<MapDefinition>,
+    mut vfx_events: ResMut<Events<SpawnVfxEvent>>,
+    game_time: Res<GameTime>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
     mut spawned_abilities: Query<(Entity, &mut SpawnedAbility)>,
-    mut players: Query<Without<Incapacitated, (&Player, &mut Health)>>,
+    mut players: Query<Without<Incapacitated, (&Player, &mut Health, &Transform)>>,
     mut incapacitated_players: Query<(&Player, &mut Incapacitated)>,
-    mut enemies: Query<(&Enemy, &Defence, &mut Health, &Transform)>,
+    mut enemies: Query<(Entity, &Enemy, &Defence, &mut Health, &Transform)>,
 ) {
     for (_entityTODO_USE_TO_DESPAWN_AND_REMOVE_ABILITY_APPLIED, mut ability) in
         &mut spawned_abilities.iter()
@@ -534,7 +524,7 @@ pub fn execute_abilities(
             match effect {
                 AbilityDetail::Buff(_) => todo!("Need a way to target a buff? No time!"),
                 AbilityDetail::Attack(data) => {
-                    for (enemy, defence, mut health, tx) in &mut enemies.iter() {
+                    for (entity, enemy, defence, mut health, tx) in &mut enemies.iter() {
                         // wrong lane
                         if enemy.lane != ability.lane {
                             continue;
@@ -542,7 +532,7 @@ pub fn execute_abilities(
 
                         // out of range
                         // TODO - probably need to include player offset here as well?
-                        if (tx.translation().y() - TARGET_LOCATIONS[ability.lane].1).abs()
+                        if (tx.translation().y() - map.target_location(ability.lane).y()).abs()
                             > MELEE_RANGE
                         {
                             continue;
@@ -550,22 +540,33 @@ pub fn execute_abilities(
 
                         // resolve combat
                         // TODO - don't do this in here, as its editing the health from too many places
-                        let result = resolve_combat(
+                        let result = calculator.resolve(
                             &BaseAttack {
                                 min_attack_damage: data.min_damage,
                                 max_attack_damage: data.max_damage,
+                                damage_type: data.damage_type,
                                 ..Default::default()
                             },
                             defence,
                         );
+                        let was_alive = health.target_health > 0.;
                         health.target_health -= result.damage as f32;
+                        text_events.send(FloatingTextEvent::damage(&result, tx.translation()));
+                        combat_events.send(CombatEvent::damage(
+                            game_time.elapsed_time,
+                            Combatant::Hero(ability.player_id),
+                            Combatant::Enemy(entity),
+                            Some(ability.ability_id),
+                            &result,
+                            was_alive && health.target_health <= 0.,
+                        ));
 
                         // just apply to the first available
                         break;
                     }
                 }
                 AbilityDetail::AttackArea(data, range) => {
-                    for (enemy, defence, mut health, tx) in &mut enemies.iter() {
+                    for (entity, enemy, defence, mut health, tx) in &mut enemies.iter() {
                         // wrong lane
                         if enemy.lane != ability.lane {
                             continue;
@@ -573,7 +574,7 @@ pub fn execute_abilities(
 
                         // out of range
                         // TODO - probably need to include player offset here as well?
-                        if (tx.translation().y() - TARGET_LOCATIONS[ability.lane].1).abs()
+                        if (tx.translation().y() - map.target_location(ability.lane).y()).abs()
                             > *range as f32
                         {
                             continue;
@@ -581,24 +582,44 @@ pub fn execute_abilities(
 
                         // resolve combat
                         // TODO - don't do this in here, as its editing the health from too many places
-                        let result = resolve_combat(
+                        let result = calculator.resolve(
                             &BaseAttack {
                                 min_attack_damage: data.min_damage,
                                 max_attack_damage: data.max_damage,
+                                damage_type: data.damage_type,
                                 ..Default::default()
                             },
                             defence,
                         );
+                        let was_alive = health.target_health > 0.;
                         health.target_health -= result.damage as f32;
+                        text_events.send(FloatingTextEvent::damage(&result, tx.translation()));
+                        combat_events.send(CombatEvent::damage(
+                            game_time.elapsed_time,
+                            Combatant::Hero(ability.player_id),
+                            Combatant::Enemy(entity),
+                            Some(ability.ability_id),
+                            &result,
+                            was_alive && health.target_health <= 0.,
+                        ));
                     }
                 }
                 AbilityDetail::Heal(data) => {
-                    for (player, mut health) in &mut players.iter() {
+                    for (player, mut health, tx) in &mut players.iter() {
                         if player.current_lane != ability.lane {
                             continue;
                         }
 
                         health.target_health += data.burst_heal;
+                        combat_events.send(CombatEvent::heal(
+                            game_time.elapsed_time,
+                            Combatant::Hero(ability.player_id),
+                            Combatant::Hero(player.player_id),
+                            Some(ability.ability_id),
+                            data.burst_heal,
+                        ));
+                        text_events
+                            .send(FloatingTextEvent::heal(data.burst_heal, tx.translation()));
                     }
                 }
                 AbilityDetail::Revive(_) => {
@@ -610,22 +631,51 @@ pub fn execute_abilities(
                         incap.is_revived = true;
                     }
                 }
-                AbilityDetail::SpawnAnimation(atlas_id, frame_start, frame_end) => {
-                    let pos: Vec3 = Vec2::from(TARGET_LOCATIONS[ability.lane]).extend(0.);
-                    let atlas = match *atlas_id {
-                        FLAME_WALL_ID => assets.flame_wall_atlas,
-                        HEAL_ID => assets.heal_atlas,
-                        _ => assets.splatter_atlas,
-                    };
-
-                    spawn_animated_spritesheet(
-                        &mut commands,
-                        atlas,
-                        0.1,
-                        vec![(*frame_start, *frame_end)],
-                        pos,
-                        true,
-                    );
+                AbilityDetail::Taunt(threat) => {
+                    for (entity, enemy, _, _, _) in &mut enemies.iter() {
+                        if enemy.lane != ability.lane {
+                            continue;
+                        }
+
+                        combat_events.send(CombatEvent::taunt(
+                            game_time.elapsed_time,
+                            Combatant::Hero(ability.player_id),
+                            Combatant::Enemy(entity),
+                            Some(ability.ability_id),
+                            *threat,
+                        ));
+                    }
+                }
+                AbilityDetail::Stun(duration) => {
+                    for (entity, enemy, _, health, tx) in &mut enemies.iter() {
+                        if enemy.lane != ability.lane || health.target_health <= 0. {
+                            continue;
+                        }
+
+                        if (tx.translation().y() - map.target_location(ability.lane).y()).abs()
+                            > MELEE_RANGE
+                        {
+                            continue;
+                        }
+
+                        // the stun system interrupts the enemy's swing
+                        commands.insert_one(
+                            entity,
+                            Stunned {
+                                end_time: game_time.elapsed_time + duration,
+                            },
+                        );
+                        text_events.send(FloatingTextEvent::status("Stunned!", tx.translation()));
+
+                        // just apply to the first available, like an attack
+                        break;
+                    }
+                }
+                AbilityDetail::SpawnEffect(name) => {
+                    let pos: Vec3 = map
+                        .target_location(ability.lane)
+                        .extend(GAME_ELEMENT_LAYER + 1.);
+                    vfx_events.send(SpawnVfxEvent::at(name, pos));
                 }
             };
 
@@ -1,37 +1,71 @@
 use crate::assets::MaterialsAndTextures;
+use crate::{
+    abilities::ability_data::AbilityDatabase,
+    hero_classes::{HeroClassDatabase, Party},
+    map::MapDefinition,
+};
 use crate::{
     components::{GameSceneConfigured, GameSceneEntity, HealthBar, MainGameSidebarUi},
     game_ui::spawn_player_sidebar,
 };
 use bevy::prelude::*;
 use bevy_ninepatch::NinePatchBuilder;
-use spectre_animations::spawn_animated_spritesheet;
-use spectre_random::RNG;
+use spectre_animations::{prelude::VfxInstance, spawn_animated_spritesheet, AnimationLibrary};
 use spectre_state::*;
 use spectre_time::{GameSpeedRequest, GameTime};
 
 use crate::{components::CurrentWave, constants::*};
-use crate::{game_ui::spawn_ui, player_factory::get_player};
+use crate::{
+    game_ui::spawn_ui,
+    player_factory::{get_player, get_player_animations, get_player_clips},
+};
 
 use super::MyGameScenes;
 
 fn spawn_player(
     mut commands: &mut Commands,
     assets: &Res<MaterialsAndTextures>,
-    texture_atlas_handle: Handle<TextureAtlas>,
+    animations: &Res<AnimationLibrary>,
+    map: &MapDefinition,
+    classes: &HeroClassDatabase,
+    abilities: &AbilityDatabase,
+    class_id: &str,
+    hotkeys: Vec<String>,
     player_id: u8,
     lane: usize,
 ) {
+    let class = match classes.get(class_id) {
+        None => {
+            println!(
+                "Unknown hero class {}, not spawning player {}",
+                class_id, player_id
+            );
+            return;
+        }
+        Some(class) => class,
+    };
+
+    let texture_atlas_handle = match animations.atlas(&class.sprite) {
+        None => {
+            println!(
+                "Unknown sprite {} for hero class {}",
+                class.sprite, class_id
+            );
+            return;
+        }
+        Some(atlas) => atlas,
+    };
+
     let player_entity = spawn_animated_spritesheet(
         &mut commands,
         texture_atlas_handle,
-        0.75,
-        vec![(0, 1), (2, 3)], // idle, dead
-        Vec2::from(TARGET_LOCATIONS[lane]).extend(GAME_ELEMENT_LAYER)
-            - Vec3::new(0., PLAYER_OFFSET_Y, 0.),
-        false,
+        get_player_clips(animations.clips(texture_atlas_handle)),
+        map.target_location(lane).extend(GAME_ELEMENT_LAYER) - Vec3::new(0., PLAYER_OFFSET_Y, 0.),
     )
-    .with_bundle(get_player(player_id, lane))
+    .with_bundle(get_player(
+        player_id, lane, class_id, class, hotkeys, abilities,
+    ))
+    .with(get_player_animations())
     .with(GameSceneEntity)
     .current_entity()
     .unwrap();
@@ -54,8 +88,13 @@ pub fn setup_game_scene(
     mut waves: ResMut<CurrentWave>,
     mut is_configured: ResMut<GameSceneConfigured>,
     assets: Res<MaterialsAndTextures>,
+    animations: Res<AnimationLibrary>,
     game_time: Res<GameTime>,
     game_state: Res<GameState<MyGameScenes>>,
+    map: Res<MapDefinition>,
+    party: Res<Party>,
+    classes: Res<HeroClassDatabase>,
+    abilities: Res<AbilityDatabase>,
     nine_patches: ResMut<Assets<NinePatchBuilder<()>>>,
     mut sidebar_components: Query<(Entity, &MainGameSidebarUi)>,
 ) {
@@ -65,13 +104,22 @@ pub fn setup_game_scene(
         return;
     }
 
+    let portraits: Vec<Handle<ColorMaterial>> = party
+        .classes
+        .iter()
+        .map(|id| match classes.get(id) {
+            None => assets.char1_portrait_material,
+            Some(class) => assets.portrait(&class.portrait),
+        })
+        .collect();
+
     if is_configured.0 == true {
         println!("Skipping full game scene configuration");
 
         println!("Respawning player GUI");
 
         for (parent, _) in &mut sidebar_components.iter() {
-            spawn_player_sidebar(parent, &mut commands, &assets);
+            spawn_player_sidebar(parent, &mut commands, &assets, &portraits);
             break; // really only should be one, need a neater way to do this
         }
 
@@ -90,141 +138,54 @@ pub fn setup_game_scene(
 
     // spawn the UI
     // NOTE: this moves materials. it shouldn't
-    let entity = spawn_ui(&mut commands, &assets, nine_patches, assets.ui_material);
+    let entity = spawn_ui(
+        &mut commands,
+        &assets,
+        nine_patches,
+        assets.ui_material,
+        &portraits,
+    );
     commands.insert_one(entity, GameSceneEntity); // mark for cleanup
 
-    // spawn a sample game entity with easing
-    spawn_player(&mut commands, &assets, assets.char1_atlas, 0, 0);
-    spawn_player(&mut commands, &assets, assets.char2_atlas, 1, 1);
-    spawn_player(&mut commands, &assets, assets.char3_atlas, 2, 2);
-
-    // spawn "world"
-    commands
-        .spawn(SpriteComponents {
-            material: assets.canyon_material,
-            transform: Transform::from_translation(Vec3::new(
-                -270.,
-                300.,
-                GAME_ELEMENT_LAYER - 0.5,
-            )),
-            ..Default::default()
-        })
-        .with(GameSceneEntity)
-        .spawn(SpriteComponents {
-            material: assets.boulder_material,
-            transform: Transform::from_translation(Vec3::new(50., 150., GAME_ELEMENT_LAYER + 0.5)),
-            ..Default::default()
-        })
-        .with(GameSceneEntity);
+    // spawn the party, spread out across the lanes
+    let hotkeys = party.hotkeys(&classes);
+    for (player_id, (class_id, keys)) in party.classes.iter().zip(hotkeys).enumerate() {
+        spawn_player(
+            &mut commands,
+            &assets,
+            &animations,
+            &map,
+            &classes,
+            &abilities,
+            class_id,
+            keys,
+            player_id as u8,
+            map.starting_lane(player_id as u8),
+        );
+    }
 
-    let trees: [[f32; 4]; 15] = [
-        [
-            -240.,
-            320.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -210.,
-            270.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            80.,
-            170.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            100.,
-            160.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -50.,
-            -220.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            200.,
-            140.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -340.,
-            -110.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -75.,
-            -75.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            250.,
-            -270.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -250.,
-            -220.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -280.,
-            -120.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            110.,
-            270.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            280.,
-            70.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -310.,
-            50.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-        [
-            -450.,
-            -20.,
-            RNG::f32_between(0., 3.14),
-            RNG::f32_between(0.85, 1.05),
-        ],
-    ];
-    trees.iter().for_each(|[x, y, rot, scale]| {
-        commands
-            .spawn(SpriteComponents {
-                material: assets.tree_material,
-                transform: Transform::from_translation(Vec3::new(*x, *y, GAME_ELEMENT_LAYER + 0.5))
-                    .with_rotation(Quat::from_axis_angle(Vec3::unit_z(), *rot))
-                    .with_scale(*scale),
-                ..Default::default()
-            })
-            .with(GameSceneEntity);
-    });
+    // spawn the scenery and the obelisk from the map
+    for decoration in map.decorations.iter() {
+        match assets.decoration(&decoration.sprite) {
+            None => println!("Unknown decoration sprite {}", decoration.sprite),
+            Some(material) => {
+                commands
+                    .spawn(SpriteComponents {
+                        material,
+                        transform: decoration.transform(),
+                        ..Default::default()
+                    })
+                    .with(GameSceneEntity);
+            }
+        }
+    }
 
     commands
         .spawn(SpriteComponents {
             material: assets.obelisk_material,
-            transform: Transform::from_translation(Vec3::new(-180., -300., GAME_ELEMENT_LAYER)),
+            transform: Transform::from_translation(
+                map.obelisk_location().extend(GAME_ELEMENT_LAYER),
+            ),
             ..Default::default()
         })
         .with(GameSceneEntity);
@@ -246,6 +207,7 @@ pub fn teardown_game_scene(
     mut commands: Commands,
     game_state: Res<GameState<MyGameScenes>>,
     mut loading_scene_items: Query<(Entity, &GameSceneEntity)>,
+    mut effects: Query<(Entity, &VfxInstance)>,
 ) {
     if !game_state.is_in_scene(&MyGameScenes::Game)
         || !game_state.is_in_status(&GameStatus::Exiting)
@@ -270,4 +232,9 @@ pub fn teardown_game_scene(
     for (entity, _) in &mut loading_scene_items.iter() {
         commands.despawn_recursive(entity);
     }
+
+    // empty the effect pool, it is refilled as effects are requested
+    for (entity, _) in &mut effects.iter() {
+        commands.despawn(entity);
+    }
 }
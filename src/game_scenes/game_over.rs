@@ -1,4 +1,10 @@
-use crate::{components::GameSceneConfigured, waves::WAVE_DATA};
+use crate::{
+    combat_log::CombatLog,
+    components::GameSceneConfigured,
+    endless::GameMode,
+    high_scores::{HighScore, HighScores},
+    waves::WaveDatabase,
+};
 use bevy::prelude::*;
 use spectre_state::*;
 
@@ -32,7 +38,11 @@ pub fn setup_gameover_scene(
     game_state: Res<GameState<MyGameScenes>>,
     mut player_score: ResMut<PlayerScore>,
     mut waves: ResMut<CurrentWave>,
+    wave_data: Res<WaveDatabase>,
+    mode: Res<GameMode>,
+    mut high_scores: ResMut<HighScores>,
     mut is_configured: ResMut<GameSceneConfigured>,
+    mut combat_log: ResMut<CombatLog>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     button_materials: Res<ButtonMaterials>,
     asset_server: Res<AssetServer>,
@@ -44,6 +54,55 @@ pub fn setup_gameover_scene(
     }
 
     let font_handle = asset_server.load("assets/fonts/teletactile.ttf").unwrap();
+
+    let breakdown = combat_log
+        .heroes()
+        .iter()
+        .map(|hero| {
+            let summary = combat_log.hero_summary(*hero);
+            format!(
+                "Hero {}: {} damage dealt, {} taken, {:.0} healed, {} kills, {} obelisk damage prevented",
+                hero + 1,
+                summary.damage_dealt,
+                summary.damage_taken,
+                summary.healing_done,
+                summary.kills,
+                summary.obelisk_damage_prevented
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let summary = match &*mode {
+        GameMode::Story => {
+            if waves.wave_idx >= wave_data.waves.len() {
+                "You Surived and broke the loop!".to_string()
+            } else {
+                "The obelisk was destroyed. We may never know its secrets.".to_string()
+            }
+        }
+        GameMode::Endless(run) => {
+            let placed = high_scores.record(HighScore {
+                days_survived: run.days_survived(),
+                xp: player_score.xp,
+                seed: run.seed,
+                difficulty: run.difficulty,
+            });
+            high_scores.save();
+
+            match placed {
+                Some(0) => format!(
+                    "The obelisk fell after {} days. A new high score!",
+                    run.days_survived()
+                ),
+                _ => format!(
+                    "The obelisk fell after {} days. The best run lasted {} days.",
+                    run.days_survived(),
+                    high_scores.best().unwrap().days_survived
+                ),
+            }
+        }
+    };
+
     commands
         .spawn(NodeComponents {
             style: Style {
@@ -92,11 +151,7 @@ pub fn setup_gameover_scene(
                         ..Default::default()
                     },
                     text: Text {
-                        value: if waves.wave_idx >= WAVE_DATA.len() {
-                            "You Surived and broke the loop!".to_string()
-                        } else {
-                            "The obelisk was destroyed. We may never know its secrets.".to_string()
-                        },
+                        value: summary,
                         font: font_handle,
                         style: TextStyle {
                             font_size: 20.0,
@@ -106,16 +161,36 @@ pub fn setup_gameover_scene(
                     ..Default::default()
                 })
                 .with(MenuButtonText);
+
+            for line in breakdown.iter() {
+                parent.spawn(TextComponents {
+                    style: Style {
+                        align_self: AlignSelf::Center,
+                        margin: Rect::all(Val::Px(5.)),
+                        ..Default::default()
+                    },
+                    text: Text {
+                        value: line.clone(),
+                        font: font_handle,
+                        style: TextStyle {
+                            font_size: 14.0,
+                            color: Color::rgb(0.8, 0.8, 0.8),
+                        },
+                    },
+                    ..Default::default()
+                });
+            }
         })
         .with(GameOverSceneEntity);
 
     // reset state to allow replay
     player_score.xp = 0;
-    player_score.obelisk_health = 1000;
+    player_score.obelisk_health = player_score.max_obelisk_health;
     player_score.game_over = false;
     waves.wave_idx = 0;
     waves.next_wave_time = 0.;
     is_configured.0 = false;
+    combat_log.clear();
 }
 
 pub fn teardown_gameover_scene(
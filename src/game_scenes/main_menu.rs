@@ -1,32 +1,236 @@
 use bevy::prelude::*;
+use spectre_random::RNG;
 use spectre_state::*;
 
+use crate::{
+    components::PlayerScore,
+    difficulty::Difficulty,
+    endless::{EndlessRun, GameMode},
+    hero_classes::{HeroClassDatabase, Party},
+    map::{MapDatabase, MapDefinition, SelectedMap},
+};
+
 use super::{ButtonMaterials, MyGameScenes};
 
 pub struct MainMenuSceneEntity;
 pub struct MenuButtonText;
 
+/// Flags the text showing the selected difficulty
+pub struct DifficultyButtonText;
+
+/// Flags the text showing the selected map
+pub struct MapButtonText;
+
+/// Flags the text showing the class picked for a party slot
+pub struct HeroButtonText(pub usize);
+
+/// Flags the row holding a button for each party slot, rebuilt when the map changes
+pub struct PartyButtonRow;
+
+/// Links a main menu button to the game mode it starts, cycling the difficulty, the map or
+/// the class in a party slot
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MenuButton {
+    Story,
+    Endless,
+    Difficulty,
+    Map,
+    Hero(usize),
+}
+
+/// the name of a class to show in the menu, the data files may not have loaded yet
+fn class_name(classes: &HeroClassDatabase, id: &str) -> String {
+    match classes.get(id) {
+        None => id.to_string(),
+        Some(class) => class.name.clone(),
+    }
+}
+
+fn hero_button(button_materials: &ButtonMaterials) -> ButtonComponents {
+    ButtonComponents {
+        style: Style {
+            size: Size::new(Val::Px(120.0), Val::Px(45.0)),
+            margin: Rect::all(Val::Px(5.)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        },
+        material: button_materials.normal,
+        ..Default::default()
+    }
+}
+
+fn hero_button_text(font: Handle<Font>, value: String) -> TextComponents {
+    TextComponents {
+        text: Text {
+            value,
+            font,
+            style: TextStyle {
+                font_size: 14.0,
+                color: Color::rgb(0.8, 0.8, 0.8),
+            },
+        },
+        ..Default::default()
+    }
+}
+
+/// replaces the hero buttons with one for each slot in the party
+fn rebuild_party_buttons(
+    commands: &mut Commands,
+    row: Entity,
+    old_buttons: Vec<Entity>,
+    party: &Party,
+    classes: &HeroClassDatabase,
+    button_materials: &ButtonMaterials,
+    font: Handle<Font>,
+) {
+    for entity in old_buttons {
+        commands.despawn_recursive(entity);
+    }
+
+    let mut buttons = vec![];
+    for slot in 0..party.classes.len() {
+        let button = commands
+            .spawn(hero_button(button_materials))
+            .with(MenuButton::Hero(slot))
+            .current_entity()
+            .unwrap();
+        commands.with_children(|button_parent| {
+            button_parent
+                .spawn(hero_button_text(
+                    font,
+                    class_name(classes, &party.classes[slot]),
+                ))
+                .with(HeroButtonText(slot));
+        });
+        buttons.push(button);
+    }
+
+    commands.push_children(row, &buttons);
+}
+
 pub fn run_menu_scene(
+    mut commands: Commands,
     mut game_state: ResMut<GameState<MyGameScenes>>,
-    mut interaction_query: Query<(&Button, Mutated<Interaction>)>,
+    mut mode: ResMut<GameMode>,
+    mut difficulty: ResMut<Difficulty>,
+    mut player_score: ResMut<PlayerScore>,
+    mut party: ResMut<Party>,
+    mut selected_map: ResMut<SelectedMap>,
+    mut map: ResMut<MapDefinition>,
+    classes: Res<HeroClassDatabase>,
+    maps: Res<MapDatabase>,
+    button_materials: Res<ButtonMaterials>,
+    asset_server: Res<AssetServer>,
+    mut interaction_query: Query<(&Button, Mutated<Interaction>, &MenuButton)>,
+    mut difficulty_text: Query<With<DifficultyButtonText, &mut Text>>,
+    mut map_text: Query<With<MapButtonText, &mut Text>>,
+    mut hero_text: Query<(&mut Text, &HeroButtonText)>,
+    mut party_rows: Query<(Entity, &PartyButtonRow)>,
+    mut menu_buttons: Query<(Entity, &MenuButton)>,
 ) {
     if !game_state.is_in_scene(&MyGameScenes::Menu) {
         return;
     }
 
-    for (_button, interaction) in &mut interaction_query.iter() {
+    let mut map_changed = false;
+    for (_button, interaction, menu_button) in &mut interaction_query.iter() {
         match *interaction {
-            Interaction::Clicked => {
-                game_state.set_transition(MyGameScenes::Game);
-            }
+            Interaction::Clicked => match menu_button {
+                MenuButton::Difficulty => {
+                    difficulty.preset = difficulty.preset.next();
+                    for mut text in &mut difficulty_text.iter() {
+                        text.value = difficulty.preset.name().to_string();
+                    }
+                }
+                MenuButton::Map => {
+                    selected_map.cycle(&maps);
+                    match maps.get(&selected_map.0) {
+                        None => println!("Unknown map {}", selected_map.0),
+                        Some(selected) => *map = selected.clone(),
+                    }
+
+                    party.resize(map.party_size);
+                    for mut text in &mut map_text.iter() {
+                        text.value = map.name.clone();
+                    }
+                    map_changed = true;
+                }
+                MenuButton::Hero(slot) => {
+                    party.cycle(*slot, &classes);
+                    for (mut text, hero) in &mut hero_text.iter() {
+                        if hero.0 == *slot {
+                            text.value = class_name(&classes, &party.classes[*slot]);
+                        }
+                    }
+                }
+                MenuButton::Story | MenuButton::Endless => {
+                    *mode = match menu_button {
+                        MenuButton::Endless => {
+                            GameMode::Endless(EndlessRun::new(RNG::seed(), difficulty.preset))
+                        }
+                        _ => GameMode::Story,
+                    };
+
+                    // the map data may have finished loading after the menu was set up
+                    match maps.get(&selected_map.0) {
+                        None => {}
+                        Some(selected) => *map = selected.clone(),
+                    }
+                    party.resize(map.party_size);
+
+                    player_score.max_obelisk_health =
+                        difficulty.modifiers().obelisk_health(map.obelisk.health);
+                    player_score.obelisk_health = player_score.max_obelisk_health;
+                    game_state.set_transition(MyGameScenes::Game);
+                }
+            },
             _ => {}
         }
     }
+
+    if !map_changed {
+        return;
+    }
+
+    // the party size may have changed, so rebuild the hero buttons
+    let old_buttons: Vec<Entity> = menu_buttons
+        .iter()
+        .iter()
+        .filter(|(_, button)| match button {
+            MenuButton::Hero(_) => true,
+            _ => false,
+        })
+        .map(|(entity, _)| entity)
+        .collect();
+
+    // there is only one party row
+    let row = match party_rows.iter().iter().next() {
+        None => return,
+        Some((row, _)) => row,
+    };
+
+    let font_handle = asset_server.load("assets/fonts/teletactile.ttf").unwrap();
+    rebuild_party_buttons(
+        &mut commands,
+        row,
+        old_buttons,
+        &party,
+        &classes,
+        &button_materials,
+        font_handle,
+    );
 }
 
 pub fn setup_menu_scene(
     mut commands: Commands,
     game_state: Res<GameState<MyGameScenes>>,
+    difficulty: Res<Difficulty>,
+    selected_map: Res<SelectedMap>,
+    maps: Res<MapDatabase>,
+    mut map: ResMut<MapDefinition>,
+    mut party: ResMut<Party>,
+    classes: Res<HeroClassDatabase>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     button_materials: Res<ButtonMaterials>,
     asset_server: Res<AssetServer>,
@@ -37,6 +241,12 @@ pub fn setup_menu_scene(
         return;
     }
 
+    match maps.get(&selected_map.0) {
+        None => {}
+        Some(selected) => *map = selected.clone(),
+    }
+    party.resize(map.party_size);
+
     let font_handle = asset_server.load("assets/fonts/teletactile.ttf").unwrap();
     commands
         .spawn(NodeComponents {
@@ -80,6 +290,111 @@ pub fn setup_menu_scene(
                         ..Default::default()
                     });
                 })
+                .with(MenuButton::Story)
+                .spawn(ButtonComponents {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                        margin: Rect::all(Val::Px(15.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: button_materials.normal,
+                    ..Default::default()
+                })
+                .with_children(|button_parent| {
+                    button_parent.spawn(TextComponents {
+                        text: Text {
+                            value: "Endless".to_string(),
+                            font: font_handle,
+                            style: TextStyle {
+                                font_size: 20.0,
+                                color: Color::rgb(0.8, 0.8, 0.8),
+                            },
+                        },
+                        ..Default::default()
+                    });
+                })
+                .with(MenuButton::Endless)
+                .spawn(ButtonComponents {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                        margin: Rect::all(Val::Px(15.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: button_materials.normal,
+                    ..Default::default()
+                })
+                .with_children(|button_parent| {
+                    button_parent
+                        .spawn(TextComponents {
+                            text: Text {
+                                value: difficulty.preset.name().to_string(),
+                                font: font_handle,
+                                style: TextStyle {
+                                    font_size: 20.0,
+                                    color: Color::rgb(0.8, 0.8, 0.8),
+                                },
+                            },
+                            ..Default::default()
+                        })
+                        .with(DifficultyButtonText);
+                })
+                .with(MenuButton::Difficulty)
+                .spawn(ButtonComponents {
+                    style: Style {
+                        size: Size::new(Val::Px(150.0), Val::Px(65.0)),
+                        margin: Rect::all(Val::Px(15.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        ..Default::default()
+                    },
+                    material: button_materials.normal,
+                    ..Default::default()
+                })
+                .with_children(|button_parent| {
+                    button_parent
+                        .spawn(TextComponents {
+                            text: Text {
+                                value: map.name.clone(),
+                                font: font_handle,
+                                style: TextStyle {
+                                    font_size: 20.0,
+                                    color: Color::rgb(0.8, 0.8, 0.8),
+                                },
+                            },
+                            ..Default::default()
+                        })
+                        .with(MapButtonText);
+                })
+                .with(MenuButton::Map)
+                .spawn(NodeComponents {
+                    style: Style {
+                        flex_direction: FlexDirection::Row,
+                        ..Default::default()
+                    },
+                    material: materials.add(Color::NONE.into()),
+                    ..Default::default()
+                })
+                .with_children(|row_parent| {
+                    // pick the class for each hero, the same class can be picked twice
+                    for slot in 0..party.classes.len() {
+                        row_parent
+                            .spawn(hero_button(&button_materials))
+                            .with_children(|button_parent| {
+                                button_parent
+                                    .spawn(hero_button_text(
+                                        font_handle,
+                                        class_name(&classes, &party.classes[slot]),
+                                    ))
+                                    .with(HeroButtonText(slot));
+                            })
+                            .with(MenuButton::Hero(slot));
+                    }
+                })
+                .with(PartyButtonRow)
                 .spawn(TextComponents {
                     style: Style {
                         align_self: AlignSelf::Center,
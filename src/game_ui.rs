@@ -53,6 +53,7 @@ pub fn spawn_ui(
     assets: &Res<MaterialsAndTextures>,
     mut nine_patches: ResMut<Assets<NinePatchBuilder<()>>>,
     transparent_material: Handle<ColorMaterial>,
+    portraits: &[Handle<ColorMaterial>],
 ) -> Entity {
     // TODO: store on a resource and get only once?
     let nine_patch_handle = nine_patches.add(NinePatchBuilder::by_margins(
@@ -109,7 +110,7 @@ pub fn spawn_ui(
         .current_entity()
         .unwrap();
 
-    spawn_player_sidebar(sidebar_entity, commands, &assets);
+    spawn_player_sidebar(sidebar_entity, commands, &assets, portraits);
 
     root_entity
 }
@@ -118,12 +119,20 @@ pub fn spawn_player_sidebar(
     parent: Entity,
     commands: &mut Commands,
     assets: &Res<MaterialsAndTextures>,
+    portraits: &[Handle<ColorMaterial>],
 ) {
-    let obelisk_ui = spawn_obelisk_ui(commands, assets);
-    let player_2 = spawn_player_ui(commands, assets, assets.char3_portrait_material, 2);
-    let player_1 = spawn_player_ui(commands, assets, assets.char2_portrait_material, 1);
-    let player_0 = spawn_player_ui(commands, assets, assets.char1_portrait_material, 0);
-    commands.push_children(parent, &[obelisk_ui, player_2, player_1, player_0]);
+    // the sidebar is a column, so add the players last first
+    let mut children = vec![spawn_obelisk_ui(commands, assets)];
+    for (player_id, portrait) in portraits.iter().enumerate().rev() {
+        children.push(spawn_player_ui(
+            commands,
+            assets,
+            *portrait,
+            player_id as u8,
+        ));
+    }
+
+    commands.push_children(parent, &children);
 }
 
 pub fn health_bar_system(
@@ -0,0 +1,283 @@
+/// Loads in the hero class definitions from the RON files in `assets/data`. Classes are looked
+/// up by id, i.e. "mage", and the party picked in the menu decides which three take the field
+use crate::enemy_factory::{AttackDefinition, DefenceDefinition};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_loaders::data_loaders::MergeData;
+use std::collections::HashMap;
+
+/// The hero class files to load, merged in this order
+pub const HERO_CLASS_DATA_FILES: [&str; 1] = ["assets/data/heroes.hcr"];
+
+/// The classes used to fill the party, in order
+pub const DEFAULT_PARTY: [&str; 3] = ["mage", "healer", "warrior"];
+
+/// Hotkeys given to a hero whose class hotkeys are already used by another hero in the party,
+/// i.e. when the same class is picked twice
+pub const FALLBACK_HOTKEYS: [[&str; 2]; 5] =
+    [["q", "w"], ["e", "r"], ["d", "f"], ["z", "x"], ["c", "v"]];
+
+/// Stats added each time a hero levels up
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct HeroGrowth {
+    #[serde(default)]
+    pub health: f32,
+    #[serde(default)]
+    pub mana: f32,
+    #[serde(default)]
+    pub armour: f32,
+    #[serde(default)]
+    pub damage: i32,
+}
+
+/// Describes a class of hero
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HeroClassDefinition {
+    pub name: String,
+
+    pub health: f32,
+    #[serde(default)]
+    pub health_regen: f32,
+    pub mana: f32,
+    pub movement_speed: f32,
+
+    pub attack: AttackDefinition,
+
+    #[serde(default)]
+    pub defence: DefenceDefinition,
+
+    #[serde(default)]
+    pub growth: HeroGrowth,
+
+    /// abilities the hero has when the run starts, i.e. to fill an action slot
+    #[serde(default)]
+    pub starting_abilities: Vec<u16>,
+
+    /// the first id in the class's ability tree, i.e. 1000 for the mage abilities
+    pub ability_tree: u16,
+
+    /// the portrait image key, i.e. "character1_portrait"
+    pub portrait: String,
+
+    /// the Aseprite sheet to use, i.e. "character1" for "assets/character1.json"
+    pub sprite: String,
+
+    /// the keys for each action slot, i.e. ["q", "w"]
+    pub hotkeys: Vec<String>,
+}
+
+/// A resource containing every hero class, keyed by id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct HeroClassDatabase {
+    pub classes: HashMap<String, HeroClassDefinition>,
+}
+
+impl HeroClassDatabase {
+    pub fn get(&self, id: &str) -> Option<&HeroClassDefinition> {
+        self.classes.get(id)
+    }
+
+    /// the class ids in a stable order, for cycling through in the menu
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.classes.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+impl MergeData for HeroClassDatabase {
+    fn merge(&mut self, other: &Self) {
+        for (id, class) in other.classes.iter() {
+            if self.classes.contains_key(id) {
+                println!("Duplicate hero class id {} found, replacing", id);
+            }
+
+            self.classes.insert(id.clone(), class.clone());
+        }
+    }
+}
+
+/// A resource with the class picked for each hero, by player id. The same class can be
+/// picked more than once
+pub struct Party {
+    pub classes: Vec<String>,
+}
+
+impl Default for Party {
+    fn default() -> Self {
+        Party {
+            classes: DEFAULT_PARTY.iter().map(|id| id.to_string()).collect(),
+        }
+    }
+}
+
+impl Party {
+    /// adds or removes heroes to match the map's party size, keeping the classes already picked
+    pub fn resize(&mut self, party_size: usize) {
+        while self.classes.len() < party_size {
+            let id = DEFAULT_PARTY[self.classes.len() % DEFAULT_PARTY.len()];
+            self.classes.push(id.to_string());
+        }
+
+        self.classes.truncate(party_size);
+    }
+
+    /// changes the class in a slot to the next one in the database
+    pub fn cycle(&mut self, slot: usize, database: &HeroClassDatabase) {
+        let ids = database.ids();
+        if ids.is_empty() {
+            return;
+        }
+
+        let next = match ids.iter().position(|id| *id == self.classes[slot]) {
+            None => 0,
+            Some(idx) => (idx + 1) % ids.len(),
+        };
+
+        self.classes[slot] = ids[next].clone();
+    }
+
+    /// the hotkeys for each hero. Heroes get their class hotkeys unless an earlier hero
+    /// already has them, in which case they get the first unused fallback keys
+    pub fn hotkeys(&self, database: &HeroClassDatabase) -> Vec<Vec<String>> {
+        let mut assigned: Vec<Option<Vec<String>>> = vec![];
+        for class_id in self.classes.iter() {
+            let keys = database.get(class_id).map(|class| class.hotkeys.clone());
+            let is_taken = assigned.iter().any(|other| *other == keys);
+            assigned.push(if is_taken { None } else { keys });
+        }
+
+        let fallbacks: Vec<Vec<String>> = FALLBACK_HOTKEYS
+            .iter()
+            .map(|keys| keys.iter().map(|key| key.to_string()).collect::<Vec<_>>())
+            .filter(|keys| {
+                !assigned
+                    .iter()
+                    .flatten()
+                    .any(|other| other.iter().any(|key| keys.contains(key)))
+            })
+            .collect();
+        let mut fallbacks = fallbacks.into_iter();
+
+        assigned
+            .into_iter()
+            .map(|keys| match keys {
+                Some(keys) => keys,
+                None => fallbacks.next().unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// The class of a hero, added to each player when they spawn
+pub struct HeroClass {
+    pub id: String,
+
+    /// the first id in the class's ability tree
+    pub ability_tree: u16,
+
+    /// the keys for each action slot
+    pub hotkeys: Vec<String>,
+}
+
+impl HeroClass {
+    /// the id of the ability that goes in a slot, starting from 1
+    pub fn ability_id(&self, slot_id: usize) -> u16 {
+        self.ability_tree - 1 + slot_id as u16
+    }
+
+    /// the key for an action slot, starting from 0
+    pub fn hotkey(&self, slot_id: usize) -> Option<KeyCode> {
+        self.hotkeys.get(slot_id).and_then(|key| key_code(key))
+    }
+
+    /// the key for an action slot, formatted for the UI
+    pub fn hotkey_prefix(&self, slot_id: usize) -> String {
+        match self.hotkeys.get(slot_id) {
+            None => "?".to_string(),
+            Some(key) => format!("[{}]", key.to_lowercase()),
+        }
+    }
+}
+
+/// the key code for a hotkey in a hero class file, i.e. "q"
+pub fn key_code(key: &str) -> Option<KeyCode> {
+    match key.to_lowercase().as_str() {
+        "a" => Some(KeyCode::A),
+        "b" => Some(KeyCode::B),
+        "c" => Some(KeyCode::C),
+        "d" => Some(KeyCode::D),
+        "e" => Some(KeyCode::E),
+        "f" => Some(KeyCode::F),
+        "g" => Some(KeyCode::G),
+        "h" => Some(KeyCode::H),
+        "i" => Some(KeyCode::I),
+        "j" => Some(KeyCode::J),
+        "k" => Some(KeyCode::K),
+        "l" => Some(KeyCode::L),
+        "m" => Some(KeyCode::M),
+        "n" => Some(KeyCode::N),
+        "o" => Some(KeyCode::O),
+        "p" => Some(KeyCode::P),
+        "q" => Some(KeyCode::Q),
+        "r" => Some(KeyCode::R),
+        "s" => Some(KeyCode::S),
+        "t" => Some(KeyCode::T),
+        "u" => Some(KeyCode::U),
+        "v" => Some(KeyCode::V),
+        "w" => Some(KeyCode::W),
+        "x" => Some(KeyCode::X),
+        "y" => Some(KeyCode::Y),
+        "z" => Some(KeyCode::Z),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectre_loaders::data_loaders::load_merged;
+
+    #[test]
+    fn loads_the_hero_class_files() {
+        let database = load_merged::<HeroClassDatabase>(&HERO_CLASS_DATA_FILES);
+        assert_eq!(database.ids(), vec!["healer", "mage", "warrior"]);
+
+        // the default party should match the original three heroes
+        let party = Party::default();
+        let trees: Vec<u16> = party
+            .classes
+            .iter()
+            .map(|id| database.get(id).unwrap().ability_tree)
+            .collect();
+        assert_eq!(trees, vec![1000, 2000, 3000]);
+    }
+
+    #[test]
+    fn duplicate_classes_get_unused_hotkeys() {
+        let database = load_merged::<HeroClassDatabase>(&HERO_CLASS_DATA_FILES);
+        let party = Party {
+            classes: vec![
+                "mage".to_string(),
+                "warrior".to_string(),
+                "warrior".to_string(),
+            ],
+        };
+
+        assert_eq!(
+            party.hotkeys(&database),
+            vec![vec!["q", "w"], vec!["d", "f"], vec!["e", "r"]]
+        );
+
+        let mut party = Party::default();
+        party.resize(5);
+        assert_eq!(party.classes[3..], ["mage", "healer"]);
+        assert_eq!(
+            party.hotkeys(&database)[3..],
+            [vec!["z", "x"], vec!["c", "v"]]
+        );
+
+        party.resize(2);
+        assert_eq!(party.classes, vec!["mage", "healer"]);
+    }
+}
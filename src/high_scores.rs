@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::difficulty::DifficultyPreset;
+
+/// Where high scores are saved between games
+pub const HIGH_SCORE_FILE: &str = "high_scores.ron";
+
+/// How many high scores to keep
+pub const MAX_HIGH_SCORES: usize = 10;
+
+/// The result of an endless run
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HighScore {
+    pub days_survived: usize,
+    pub xp: usize,
+
+    /// the seed for the run, so it can be replayed
+    pub seed: u64,
+
+    #[serde(default)]
+    pub difficulty: DifficultyPreset,
+}
+
+/// A resource with the best endless runs, best first
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct HighScores {
+    pub scores: Vec<HighScore>,
+}
+
+impl FromResources for HighScores {
+    fn from_resources(_: &Resources) -> Self {
+        HighScores::load()
+    }
+}
+
+impl HighScores {
+    /// loads the saved high scores, starting a new table if there are none
+    pub fn load() -> Self {
+        match std::fs::read_to_string(HIGH_SCORE_FILE) {
+            Err(_) => HighScores::default(),
+            Ok(contents) => match ron::de::from_str(&contents) {
+                Err(e) => {
+                    println!("Unable to read high scores, starting again - {:?}", e);
+                    HighScores::default()
+                }
+                Ok(scores) => scores,
+            },
+        }
+    }
+
+    pub fn save(&self) {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new()).unwrap();
+        match std::fs::write(HIGH_SCORE_FILE, contents) {
+            Err(e) => println!("Unable to save high scores - {:?}", e),
+            Ok(_) => {}
+        }
+    }
+
+    /// the most days survived in any run
+    pub fn best(&self) -> Option<&HighScore> {
+        self.scores.first()
+    }
+
+    /// adds a score to the table, returning its position if it made the table
+    pub fn record(&mut self, score: HighScore) -> Option<usize> {
+        let position = self
+            .scores
+            .iter()
+            .position(|other| (score.days_survived, score.xp) > (other.days_survived, other.xp))
+            .unwrap_or(self.scores.len());
+
+        if position >= MAX_HIGH_SCORES {
+            return None;
+        }
+
+        self.scores.insert(position, score);
+        self.scores.truncate(MAX_HIGH_SCORES);
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(days_survived: usize, xp: usize) -> HighScore {
+        HighScore {
+            days_survived,
+            xp,
+            seed: 0,
+            difficulty: DifficultyPreset::Normal,
+        }
+    }
+
+    #[test]
+    fn keeps_the_best_runs_in_order() {
+        let mut scores = HighScores::default();
+        assert_eq!(scores.record(score(3, 100)), Some(0));
+        assert_eq!(scores.record(score(5, 50)), Some(0));
+        assert_eq!(scores.record(score(3, 200)), Some(1));
+        assert_eq!(scores.best().unwrap().days_survived, 5);
+
+        for _ in 0..MAX_HIGH_SCORES {
+            scores.record(score(10, 0));
+        }
+        assert_eq!(scores.scores.len(), MAX_HIGH_SCORES);
+        assert_eq!(scores.record(score(1, 0)), None);
+    }
+}
@@ -1,37 +1,59 @@
-use abilities::{ability_data::*, systems::ability_purchase_system};
+use abilities::systems::ability_purchase_system;
 use assets::MaterialsAndTextures;
 use bevy::{prelude::*, render::pass::ClearColor, window::WindowMode};
 use bevy_ninepatch::NinePatchPlugin;
-use combat::enemy_target_selection_system;
-use combat::{dead_enemy_removal_system, enemy_auto_attack_system, player_auto_attack_system};
+use combat::{
+    dead_enemy_removal_system, enemy_auto_attack_system, player_auto_attack_system, stun_system,
+};
+use combat::{enemy_target_selection_system, ThreatEventListener};
+use combat_log::CombatLogPlugin;
 use components::PlayerScore;
 use components::{CurrentWave, GameSceneConfigured};
 use constants::*;
+use data::DataFileLoaderPlugin;
+use difficulty::Difficulty;
+use endless::GameMode;
+use enemy_behaviours::EnemyBehaviourPlugin;
 use events::*;
+use floating_text::FloatingTextPlugin;
 use game_scenes::*;
 use game_ui::health_bar_system;
+use hero_classes::Party;
+use high_scores::HighScores;
+use map::{MapDefinition, SelectedMap};
 use movement::MovementPlugin;
 use player_ui::*;
-use spectre_animations::prelude::AnimationPlugin;
+use projectiles::ProjectilePlugin;
+use spectre_animations::prelude::{AnimationPlugin, LoadAsepriteSheets};
+use spectre_combat::damage::CombatCalculator;
 use spectre_core::CharacterStatsPlugin;
 use spectre_loaders::{LoadAssets, ResourceLoaderPlugin};
 use spectre_time::GameTimePlugin;
 use systems::*;
-use waves::wave_spawning_system;
+use waves::{wave_spawning_system, SpawnQueue};
 
 mod abilities;
 mod assets;
 mod combat;
+mod combat_log;
 mod components;
 mod constants;
 mod data;
+mod difficulty;
+mod endless;
+mod enemy_behaviours;
 mod enemy_factory;
 mod events;
+mod floating_text;
 mod game_scenes;
 mod game_ui;
+mod hero_classes;
+mod high_scores;
+mod map;
 mod movement;
 mod player_factory;
 mod player_ui;
+mod projectiles;
 mod systems;
 mod waves;
 
@@ -49,10 +71,19 @@ fn main() {
         //resources
         // .add_resource(ClearColor(Color::rgb_u8(8, 20, 30))) // not sure why this colour is too bright?
         .add_resource(ClearColor(Color::rgb_u8(1, 2, 3)))
-        .init_resource::<AbilityDatabase>() // loaded using asset loader
         .init_resource::<CurrentWave>()
+        .init_resource::<SpawnQueue>()
+        .init_resource::<GameMode>()
+        .init_resource::<Difficulty>()
+        .init_resource::<HighScores>()
+        .init_resource::<Party>()
+        .init_resource::<MapDefinition>()
+        .init_resource::<SelectedMap>()
         .init_resource::<GameSceneConfigured>()
         .init_resource::<PlayerScore>()
+        .init_resource::<CombatCalculator>()
+        .init_resource::<ThreatEventListener>()
+        .init_resource::<HurtEventListener>()
         // event registration
         .add_event::<WaveSpawnedEvent>()
         .init_resource::<WaveSpawnedEventListener>()
@@ -67,15 +98,20 @@ fn main() {
         .add_startup_system(setup.system())
         .add_plugin(GameTimePlugin)
         .add_plugin(ResourceLoaderPlugin)
-        // .add_plugin(DataFileLoaderPlugin)
+        .add_plugin(DataFileLoaderPlugin)
         .add_plugin(CharacterStatsPlugin)
         .add_plugin(AnimationPlugin)
+        .add_plugin(FloatingTextPlugin)
+        .add_plugin(CombatLogPlugin)
+        .add_plugin(ProjectilePlugin)
         .add_plugin(GameStatePlugin)
         .add_plugin(NinePatchPlugin::<()>::default())
         .add_plugin(MovementPlugin)
+        .add_plugin(EnemyBehaviourPlugin)
         // random systems not properly organised into plugins yet
         .add_system(wave_spawning_system.system())
         .add_stage_after("update", "dead_removal")
+        .add_system(stun_system.system())
         .add_system(player_auto_attack_system.system())
         .add_system(enemy_target_selection_system.system())
         .add_system(enemy_auto_attack_system.system())
@@ -84,6 +120,8 @@ fn main() {
         .add_system(update_player_mana_ui.system())
         .add_system(update_obelisk_status_text.system())
         .add_system(player_lane_change_interaction.system())
+        .add_system(player_targeting_interaction.system())
+        .add_system(update_player_targeting_ui.system())
         .add_system(ability_purchase_system.system())
         .add_system(close_ability_screen.system())
         .add_system(abort_ability_screen.system())
@@ -91,11 +129,15 @@ fn main() {
         .add_system_to_stage("dead_removal", dead_enemy_removal_system.system())
         .add_system(player_incapacitation_system.system())
         .add_system(player_revival_system.system())
+        .add_system(player_hurt_system.system())
         .init_resource::<MaterialsAndTextures>()
         .run();
 }
 
-fn setup(mut commands: Commands) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    // hot reload data files (and textures) while the game is running
+    asset_server.watch_for_changes().unwrap();
+
     // spawn the camera
     commands
         .spawn(Camera2dComponents::default())
@@ -129,6 +171,46 @@ fn setup(mut commands: Commands) {
             .map(|a| a.into())
             .collect(),
         },))
+        .spawn((LoadAsepriteSheets {
+            sheets: vec![
+                (
+                    "assets/character1.json",
+                    CHARACTER_1_SPRITE,
+                    CHARACTER_1_ATLAS,
+                ),
+                (
+                    "assets/character2.json",
+                    CHARACTER_2_SPRITE,
+                    CHARACTER_2_ATLAS,
+                ),
+                (
+                    "assets/character3.json",
+                    CHARACTER_3_SPRITE,
+                    CHARACTER_3_ATLAS,
+                ),
+                (
+                    "assets/enemy_wolf.json",
+                    ENEMY_WOLF_SPRITE,
+                    ENEMY_WOLF_ATLAS,
+                ),
+                (
+                    "assets/enemy_bear.json",
+                    ENEMY_BEAR_SPRITE,
+                    ENEMY_BEAR_ATLAS,
+                ),
+                (
+                    "assets/enemy_troll.json",
+                    ENEMY_TROLL_SPRITE,
+                    ENEMY_TROLL_ATLAS,
+                ),
+                ("assets/splatter.json", SPLATTER_ID, SPLATTER_ATLAS),
+                ("assets/flame_wall.json", FLAME_WALL_ID, FLAME_WALL_ATLAS),
+                ("assets/heal.json", HEAL_ID, HEAL_ATLAS),
+            ]
+            .into_iter()
+            .map(|a| a.into())
+            .collect(),
+        },))
         .spawn((LoadAssets {
             assets: vec![
                 ("assets/audio/attacking_obelisk.mp3"),
@@ -0,0 +1,427 @@
+/// Loads in the maps from the RON files in `assets/data`. A map describes the lanes, where the
+/// obelisk sits and the scenery, so new levels can be added without code changes
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_loaders::data_loaders::MergeData;
+use spectre_random::RNG;
+use std::{collections::HashMap, ops::Range};
+
+use crate::constants::{GAME_ELEMENT_LAYER, OBELISK_HEALTH, OBELISK_REACH};
+
+/// The map files to load, merged in this order
+pub const MAP_DATA_FILES: [&str; 1] = ["assets/data/maps.mpr"];
+
+/// The map played unless another is picked in the menu
+pub const DEFAULT_MAP: &str = "canyon";
+
+/// A lane the enemies walk down
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct LaneDefinition {
+    /// the points enemies walk through, starting from where they spawn. Lanes can carry on
+    /// past the defend point, i.e. to the obelisk
+    pub path: Vec<(f32, f32)>,
+
+    /// the index of the point in the path the heroes defend
+    pub defend_point: usize,
+}
+
+impl LaneDefinition {
+    pub fn spawn(&self) -> Vec2 {
+        Vec2::from(self.path[0])
+    }
+
+    pub fn defend(&self) -> Vec2 {
+        self.point(self.defend_point)
+    }
+
+    /// a point in the path, clamped to the end of the path
+    pub fn point(&self, idx: usize) -> Vec2 {
+        Vec2::from(self.path[idx.min(self.path.len() - 1)])
+    }
+
+    /// true once a waypoint is past the last point in the path
+    pub fn is_end(&self, waypoint: usize) -> bool {
+        waypoint >= self.path.len()
+    }
+
+    /// the waypoint to walk to when joining the lane from somewhere else, i.e. after switching
+    /// lanes. This is the end of the path segment closest to the position
+    pub fn next_waypoint(&self, position: Vec2) -> usize {
+        let mut closest = (std::f32::MAX, 0);
+        for idx in 1..self.path.len() {
+            let start = self.point(idx - 1);
+            let segment = self.point(idx) - start;
+            let length_squared = segment.length_squared();
+            let along = if length_squared > 0. {
+                ((position - start).dot(segment) / length_squared)
+                    .max(0.)
+                    .min(1.)
+            } else {
+                0.
+            };
+
+            let distance = (start + segment * along - position).length();
+            if distance < closest.0 {
+                closest = (distance, idx);
+            }
+        }
+
+        closest.1
+    }
+
+    /// how far along the path the position is from the defend point when walking towards a
+    /// waypoint. Negative once past the defend point
+    pub fn distance_to_defend(&self, position: Vec2, waypoint: usize) -> f32 {
+        let defend = self.defend_point.min(self.path.len() - 1);
+        let length = |from: usize, to: usize| -> f32 {
+            (from..to)
+                .map(|idx| (self.point(idx + 1) - self.point(idx)).length())
+                .sum()
+        };
+
+        if waypoint <= defend {
+            (self.point(waypoint) - position).length() + length(waypoint, defend)
+        } else {
+            let previous = waypoint.min(self.path.len()) - 1;
+            -((position - self.point(previous)).length() + length(defend, previous))
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ObeliskDefinition {
+    pub position: (f32, f32),
+
+    /// the obelisk's health on normal difficulty
+    pub health: usize,
+}
+
+fn default_decoration_layer() -> f32 {
+    0.5
+}
+
+fn default_decoration_scale() -> (f32, f32) {
+    (1., 1.)
+}
+
+/// Scenery placed around the map. Each range is a (min, max) pair, and a value is picked at
+/// random from it whenever the map is set up
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct DecorationDefinition {
+    /// the image to show, i.e. "tree" for "assets/tree.png"
+    pub sprite: String,
+    pub position: (f32, f32),
+
+    /// how far in front of the characters to draw it, negative values are behind
+    #[serde(default = "default_decoration_layer")]
+    pub layer: f32,
+
+    /// the most the position can move in each direction
+    #[serde(default)]
+    pub jitter: (f32, f32),
+
+    /// the rotation in radians
+    #[serde(default)]
+    pub rotation: (f32, f32),
+
+    #[serde(default = "default_decoration_scale")]
+    pub scale: (f32, f32),
+}
+
+/// picks a value from a (min, max) range, which can be empty
+fn between((min, max): (f32, f32)) -> f32 {
+    if max > min {
+        RNG::f32_between(min, max)
+    } else {
+        min
+    }
+}
+
+impl DecorationDefinition {
+    /// a transform for the decoration, randomised within its ranges
+    pub fn transform(&self) -> Transform {
+        let (x, y) = self.position;
+        let (jitter_x, jitter_y) = self.jitter;
+        Transform::from_translation(Vec3::new(
+            x + between((-jitter_x, jitter_x)),
+            y + between((-jitter_y, jitter_y)),
+            GAME_ELEMENT_LAYER + self.layer,
+        ))
+        .with_rotation(Quat::from_axis_angle(
+            Vec3::unit_z(),
+            between(self.rotation),
+        ))
+        .with_scale(between(self.scale))
+    }
+}
+
+/// A resource describing the level being played, i.e. how many lanes there are and how many
+/// heroes are in the party
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MapDefinition {
+    pub name: String,
+    pub party_size: usize,
+    pub lanes: Vec<LaneDefinition>,
+    pub obelisk: ObeliskDefinition,
+
+    #[serde(default)]
+    pub decorations: Vec<DecorationDefinition>,
+}
+
+impl Default for MapDefinition {
+    /// a bare version of the canyon, used until the map files are loaded
+    fn default() -> Self {
+        let lane = |x: f32| LaneDefinition {
+            path: vec![(x, 365.), (x, -200.)],
+            defend_point: 1,
+        };
+
+        MapDefinition {
+            name: "Canyon".to_string(),
+            party_size: 3,
+            lanes: vec![lane(-480.), lane(-180.), lane(120.)],
+            obelisk: ObeliskDefinition {
+                position: (-180., -300.),
+                health: OBELISK_HEALTH,
+            },
+            decorations: vec![],
+        }
+    }
+}
+
+impl MapDefinition {
+    pub fn lane_count(&self) -> usize {
+        self.lanes.len()
+    }
+
+    /// every lane index, from left to right
+    pub fn lanes(&self) -> Range<usize> {
+        0..self.lanes.len()
+    }
+
+    pub fn is_lane(&self, lane: usize) -> bool {
+        lane < self.lanes.len()
+    }
+
+    /// the lane to spawn a wave's enemies in. Waves are written for the canyon's three lanes,
+    /// so lanes past the edge of smaller maps use the last lane
+    pub fn wave_lane(&self, lane: usize) -> usize {
+        lane.min(self.lane_count().max(1) - 1)
+    }
+
+    pub fn spawn_location(&self, lane: usize) -> Vec2 {
+        self.lanes[lane].spawn()
+    }
+
+    /// where enemies in the lane walk to, the heroes defend just below it
+    pub fn target_location(&self, lane: usize) -> Vec2 {
+        self.lanes[lane].defend()
+    }
+
+    pub fn obelisk_location(&self) -> Vec2 {
+        Vec2::from(self.obelisk.position)
+    }
+
+    /// true if something at the position can attack the obelisk. The obelisk guards the whole
+    /// bottom of the map, so only the height counts
+    pub fn obelisk_in_reach(&self, position: Vec2, attack_range: f32) -> bool {
+        (position.y() - self.obelisk.position.1).abs() < OBELISK_REACH + attack_range
+    }
+
+    /// checks the map can be played, as the lane lookups expect every lane to have a path
+    pub fn validate(&self) -> Result<(), String> {
+        if self.party_size == 0 {
+            return Err("the party size must be at least 1".to_string());
+        }
+
+        if self.lanes.is_empty() {
+            return Err("there must be at least one lane".to_string());
+        }
+
+        for (idx, lane) in self.lanes.iter().enumerate() {
+            if lane.path.is_empty() {
+                return Err(format!("lane {} has no points in its path", idx));
+            }
+
+            if lane.defend_point >= lane.path.len() {
+                return Err(format!(
+                    "lane {} defends point {} but its path only has {} points",
+                    idx,
+                    lane.defend_point,
+                    lane.path.len()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// the lane a hero starts in, spreading the party out across the lanes
+    pub fn starting_lane(&self, player_id: u8) -> usize {
+        let lane = player_id as usize * self.lane_count() / self.party_size.max(1);
+        lane.min(self.lane_count().max(1) - 1)
+    }
+}
+
+/// A resource containing every map that can be played, keyed by id
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct MapDatabase {
+    pub maps: HashMap<String, MapDefinition>,
+}
+
+impl MapDatabase {
+    pub fn get(&self, id: &str) -> Option<&MapDefinition> {
+        self.maps.get(id)
+    }
+
+    /// the map ids in a stable order, for cycling through in the menu
+    pub fn ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.maps.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+}
+
+impl MergeData for MapDatabase {
+    fn merge(&mut self, other: &Self) {
+        for (id, map) in other.maps.iter() {
+            match map.validate() {
+                Ok(_) => {}
+                Err(reason) => {
+                    println!("Map {} is invalid and won't be loaded: {}", id, reason);
+                    continue;
+                }
+            };
+
+            if self.maps.contains_key(id) {
+                println!("Duplicate map id {} found, replacing", id);
+            }
+
+            self.maps.insert(id.clone(), map.clone());
+        }
+    }
+}
+
+/// A resource with the id of the map picked in the menu
+pub struct SelectedMap(pub String);
+
+impl Default for SelectedMap {
+    fn default() -> Self {
+        SelectedMap(DEFAULT_MAP.to_string())
+    }
+}
+
+impl SelectedMap {
+    /// changes to the next map in the database
+    pub fn cycle(&mut self, database: &MapDatabase) {
+        let ids = database.ids();
+        if ids.is_empty() {
+            return;
+        }
+
+        let next = match ids.iter().position(|id| *id == self.0) {
+            None => 0,
+            Some(idx) => (idx + 1) % ids.len(),
+        };
+
+        self.0 = ids[next].clone();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spectre_loaders::data_loaders::load_merged;
+
+    #[test]
+    fn loads_the_map_files() {
+        let database = load_merged::<MapDatabase>(&MAP_DATA_FILES);
+        let canyon = database.get(DEFAULT_MAP).unwrap();
+        let bare = MapDefinition::default();
+
+        // the canyon should match the lanes which used to be hard coded
+        assert_eq!(canyon.lane_count(), bare.lane_count());
+        for lane in canyon.lanes() {
+            assert_eq!(canyon.spawn_location(lane), bare.spawn_location(lane));
+            assert_eq!(canyon.target_location(lane), bare.target_location(lane));
+        }
+        assert_eq!(canyon.obelisk_location(), bare.obelisk_location());
+        assert_eq!(canyon.obelisk.health, OBELISK_HEALTH);
+
+        assert_eq!(database.ids(), vec!["canyon", "delta", "pass"]);
+    }
+
+    #[test]
+    fn rejects_invalid_maps() {
+        assert!(MapDefinition::default().validate().is_ok());
+
+        let mut no_party = MapDefinition::default();
+        no_party.party_size = 0;
+
+        let mut no_lanes = MapDefinition::default();
+        no_lanes.lanes.clear();
+
+        let mut empty_path = MapDefinition::default();
+        empty_path.lanes[1].path.clear();
+
+        let mut bad_defend_point = MapDefinition::default();
+        bad_defend_point.lanes[2].defend_point = 2;
+
+        let mut loaded = MapDatabase::default();
+        for map in vec![no_party, no_lanes, empty_path, bad_defend_point] {
+            assert!(map.validate().is_err());
+            loaded.maps.insert(format!("map{}", loaded.maps.len()), map);
+        }
+
+        let mut database = MapDatabase::default();
+        database.merge(&loaded);
+        assert!(database.maps.is_empty());
+    }
+
+    #[test]
+    fn moves_wave_lanes_onto_smaller_maps() {
+        let database = load_merged::<MapDatabase>(&MAP_DATA_FILES);
+        let pass = database.get("pass").unwrap();
+        assert_eq!(
+            (0..3).map(|lane| pass.wave_lane(lane)).collect::<Vec<_>>(),
+            vec![0, 1, 1]
+        );
+
+        let delta = database.get("delta").unwrap();
+        assert_eq!(delta.wave_lane(2), 2);
+    }
+
+    #[test]
+    fn measures_distance_along_bends() {
+        let lane = LaneDefinition {
+            path: vec![(0., 300.), (0., 100.), (100., 0.), (100., -100.)],
+            defend_point: 2,
+        };
+
+        let distance = lane.distance_to_defend(Vec2::new(0., 200.), 1);
+        assert!((distance - (100. + 2f32.sqrt() * 100.)).abs() < 0.01);
+        assert!((lane.distance_to_defend(Vec2::new(100., -50.), 3) + 50.).abs() < 0.01);
+
+        // joining the lane heads for the end of the closest segment
+        assert_eq!(lane.next_waypoint(Vec2::new(-50., 250.)), 1);
+        assert_eq!(lane.next_waypoint(Vec2::new(60., 60.)), 2);
+        assert_eq!(lane.next_waypoint(Vec2::new(150., -80.)), 3);
+    }
+
+    #[test]
+    fn spreads_the_party_across_the_lanes() {
+        let database = load_merged::<MapDatabase>(&MAP_DATA_FILES);
+        let starting_lanes = |map: &MapDefinition| -> Vec<usize> {
+            (0..map.party_size as u8)
+                .map(|id| map.starting_lane(id))
+                .collect()
+        };
+
+        assert_eq!(starting_lanes(&MapDefinition::default()), vec![0, 1, 2]);
+        assert_eq!(starting_lanes(database.get("pass").unwrap()), vec![0, 1]);
+        assert_eq!(
+            starting_lanes(database.get("delta").unwrap()),
+            vec![0, 1, 2]
+        );
+    }
+}
@@ -1,70 +1,302 @@
 use bevy::prelude::*;
+use spectre_animations::prelude::AnimationStateMachine;
 use spectre_core::Movement;
 use spectre_time::GameTime;
 
-use crate::{components::*, constants::*};
+use crate::{
+    assets::MaterialsAndTextures,
+    components::*,
+    constants::*,
+    map::{LaneDefinition, MapDefinition},
+};
 
 pub struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(player_movement.system())
-            .add_system(enemy_movement.system());
+        app.add_event::<ArrivalEvent>()
+            .init_resource::<ArrivalListener>()
+            .add_system(player_movement.system())
+            .add_system(enemy_movement.system())
+            .add_system(obelisk_warning_system.system());
     }
 }
 
-/// moves a player between lanes
-/// ignores incapacitated players
+/// Where something arrived
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Arrival {
+    /// a hero finished moving to a lane
+    Lane(usize),
+
+    /// an enemy reached a point in its lane's path
+    Waypoint { lane: usize, waypoint: usize },
+
+    /// an enemy reached the point the heroes defend in its lane
+    DefendPoint(usize),
+
+    /// an enemy stopped to attack the obelisk
+    Obelisk,
+}
+
+/// Sent when a hero or an enemy arrives somewhere
+#[derive(Clone, Copy, Debug)]
+pub struct ArrivalEvent {
+    pub entity: Entity,
+    pub arrival: Arrival,
+}
+
+#[derive(Default)]
+pub struct ArrivalListener {
+    pub arrival_reader: EventReader<ArrivalEvent>,
+
+    /// the earliest time to warn about the obelisk again
+    pub next_warning: f32,
+}
+
+/// moves from a position towards a target, going no further than `max_distance`. Returns
+/// the new position and whether the target was reached
+pub fn move_towards(position: Vec2, target: Vec2, max_distance: f32) -> (Vec2, bool) {
+    let delta = target - position;
+    let distance = delta.length();
+    if distance <= max_distance {
+        return (target, true);
+    }
+
+    (position + delta * (max_distance / distance), false)
+}
+
+/// moves a player between the defend points of the lanes
+/// ignores incapacitated and stunned players
 pub fn player_movement(
     time: Res<GameTime>,
-    mut players: Query<Without<Incapacitated, (&Movement, &mut Player, &mut Transform)>>,
+    map: Res<MapDefinition>,
+    mut arrivals: ResMut<Events<ArrivalEvent>>,
+    mut players: Query<
+        Without<
+            Incapacitated,
+            Without<
+                Stunned,
+                (
+                    Entity,
+                    &Movement,
+                    &mut Player,
+                    &mut Transform,
+                    &mut AnimationStateMachine,
+                ),
+            >,
+        >,
+    >,
 ) {
-    for (movement, mut player, mut transform) in &mut players.iter() {
+    for (entity, movement, mut player, mut transform, mut animations) in &mut players.iter() {
         // TODO: handle multiple players in the same lane properly
-        let target_pos = TARGET_LOCATIONS[player.target_lane].0
-            + (player.player_id as f32) * PLAYER_OFFSET_X
-            - PLAYER_OFFSET_X;
-        let current_pos = transform.translation().x();
-
-        let delta = target_pos - current_pos;
+        let target = map.target_location(player.target_lane)
+            + Vec2::new(
+                (player.player_id as f32) * PLAYER_OFFSET_X - PLAYER_OFFSET_X,
+                -PLAYER_OFFSET_Y,
+            );
+        let position = transform.translation().truncate();
 
         // tick over the current lane once the player arrives
-        if delta.abs() < 3. {
+        if (target - position).length() < 3. {
+            if player.is_moving {
+                arrivals.send(ArrivalEvent {
+                    entity,
+                    arrival: Arrival::Lane(player.target_lane),
+                });
+            }
+
             player.current_lane = player.target_lane;
             player.is_moving = false;
+            animations.set("moving", false);
             continue;
         }
 
-        let max_delta = movement.movement_speed.value * time.delta;
+        // lanes can be at different heights, so move directly towards the defend point
+        let max_distance = movement.movement_speed.value.abs() * time.delta;
+        let (new_position, _) = move_towards(position, target, max_distance);
+
+        player.is_moving = true;
+        animations.set("moving", true);
+        transform.translate((new_position - position).extend(0.));
+    }
+}
+
+/// What an enemy walking down a lane does this frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EnemyStep {
+    /// walk up to this far along the path
+    Walk(f32),
 
-        // use abs as delta may be negative, i.e. -665 from target, max is 1.3
-        let mut used_delta = delta.abs().min(max_delta.abs());
-        if delta < 0. {
-            used_delta = -used_delta;
+    /// stay put, i.e. held up by the heroes or at the end of the path
+    Wait,
+
+    /// stop to attack the obelisk
+    AttackObelisk,
+}
+
+/// decides how far an enemy walks this frame. Heroes in the lane only block enemies which
+/// haven't reached the defend point yet, enemies past it carry on towards the obelisk
+pub fn enemy_step(
+    lane: &LaneDefinition,
+    enemy: &Enemy,
+    position: Vec2,
+    max_distance: f32,
+    is_defended: bool,
+    obelisk_in_reach: bool,
+) -> EnemyStep {
+    let max_distance = if enemy.waypoint > lane.defend_point {
+        if obelisk_in_reach {
+            return EnemyStep::AttackObelisk;
         }
 
-        // translate minimum of delta and max_delta
-        player.is_moving = true;
-        transform.translate(Vec3::new(used_delta, 0., 0.));
+        max_distance
+    } else if is_defended {
+        let to_defend = lane.distance_to_defend(position - enemy.offset, enemy.waypoint);
+        max_distance.min(to_defend - enemy.stand_off)
+    } else {
+        max_distance
+    };
+
+    if max_distance <= 0. || lane.is_end(enemy.waypoint) {
+        return EnemyStep::Wait;
     }
+
+    EnemyStep::Walk(max_distance)
 }
 
-/// moves an enemy towards their target position.
-/// TODO: If there is a player in the lane stop at the target position otherwise continue off screen
-pub fn enemy_movement(time: Res<GameTime>, enemy: &Enemy, mut transform: Mut<Transform>) {
-    let target_pos = enemy.target.y();
-    let current_pos = transform.translation().y();
+/// moves enemies along their lane's path. While heroes are in the lane enemies stop short of
+/// the defend point, otherwise they carry on past it until the obelisk is in reach. Stunned
+/// enemies stay put
+pub fn enemy_movement(
+    time: Res<GameTime>,
+    map: Res<MapDefinition>,
+    mut arrivals: ResMut<Events<ArrivalEvent>>,
+    mut enemies: Query<
+        Without<Stunned, (Entity, &mut Enemy, &Movement, &BaseAttack, &mut Transform)>,
+    >,
+    mut players: Query<Without<Incapacitated, &Player>>,
+) {
+    let hero_lanes: Vec<usize> = players
+        .iter()
+        .iter()
+        .map(|player| player.current_lane)
+        .collect();
+
+    for (entity, mut enemy, movement, attack, mut transform) in &mut enemies.iter() {
+        if !map.is_lane(enemy.lane) {
+            continue;
+        }
+
+        let lane = &map.lanes[enemy.lane];
+        let position = transform.translation().truncate();
+        let max_distance = match enemy_step(
+            lane,
+            &enemy,
+            position,
+            movement.movement_speed.value.abs() * time.delta,
+            hero_lanes.contains(&enemy.lane),
+            map.obelisk_in_reach(position, attack.attack_range),
+        ) {
+            EnemyStep::Wait => continue,
+            EnemyStep::AttackObelisk => {
+                if !enemy.at_obelisk {
+                    enemy.at_obelisk = true;
+                    arrivals.send(ArrivalEvent {
+                        entity,
+                        arrival: Arrival::Obelisk,
+                    });
+                }
+
+                continue;
+            }
+            EnemyStep::Walk(distance) => distance,
+        };
+
+        // stop at each point in the path rather than cutting the corner
+        let target = lane.point(enemy.waypoint) + enemy.offset;
+        let (new_position, arrived) = move_towards(position, target, max_distance);
+        transform.translate((new_position - position).extend(0.));
+        enemy.at_obelisk = false;
+
+        if !arrived {
+            continue;
+        }
+
+        let arrival = if enemy.waypoint == lane.defend_point {
+            Arrival::DefendPoint(enemy.lane)
+        } else {
+            Arrival::Waypoint {
+                lane: enemy.lane,
+                waypoint: enemy.waypoint,
+            }
+        };
+        arrivals.send(ArrivalEvent { entity, arrival });
+        enemy.waypoint += 1;
+    }
+}
 
-    let delta = target_pos - current_pos;
-    let max_delta = ENEMY_SPEED * time.delta;
+/// warns the players when enemies reach the obelisk
+pub fn obelisk_warning_system(
+    game_time: Res<GameTime>,
+    audio: Res<AudioOutput>,
+    assets: Res<MaterialsAndTextures>,
+    arrival_events: Res<Events<ArrivalEvent>>,
+    mut listener: ResMut<ArrivalListener>,
+) {
+    let mut reached_obelisk = false;
+    for event in listener.arrival_reader.iter(&arrival_events) {
+        if event.arrival == Arrival::Obelisk {
+            reached_obelisk = true;
+        }
+    }
 
-    // use abs as delta may be negative, i.e. -665 from target, max is 1.3
-    let mut used_delta = delta.abs().min(max_delta.abs());
+    if !reached_obelisk || listener.next_warning > game_time.elapsed_time {
+        return;
+    }
+
+    listener.next_warning = game_time.elapsed_time + OBELISK_WARNING_INTERVAL;
+    audio.play(assets.attacking_obelisk_audio);
+}
 
-    if delta < 0. {
-        used_delta = -used_delta;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn moves_towards_the_target_without_overshooting() {
+        let (position, arrived) = move_towards(Vec2::zero(), Vec2::new(30., 40.), 10.);
+        assert!(!arrived);
+        assert!((position - Vec2::new(6., 8.)).length() < 0.001);
+
+        let (position, arrived) = move_towards(position, Vec2::new(30., 40.), 100.);
+        assert!(arrived);
+        assert_eq!(position, Vec2::new(30., 40.));
     }
 
-    // translate minimum of delta and max_delta
-    transform.translate(Vec3::new(0., used_delta, 0.));
+    #[test]
+    fn heroes_only_block_enemies_before_the_defend_point() {
+        let lane = LaneDefinition {
+            path: vec![(0., 300.), (0., 0.), (100., -100.)],
+            defend_point: 1,
+        };
+        let mut enemy = Enemy::new(0, 10);
+
+        // walking down to the heroes, stopping at the defend point
+        let step = enemy_step(&lane, &enemy, Vec2::new(0., 5.), 10., true, false);
+        assert_eq!(step, EnemyStep::Walk(5.));
+        let step = enemy_step(&lane, &enemy, Vec2::new(0., 0.), 10., true, false);
+        assert_eq!(step, EnemyStep::Wait);
+
+        // a hero moving into the lane doesn't freeze enemies already past the defend point
+        enemy.waypoint = 2;
+        let step = enemy_step(&lane, &enemy, Vec2::new(50., -50.), 10., true, false);
+        assert_eq!(step, EnemyStep::Walk(10.));
+        let step = enemy_step(&lane, &enemy, Vec2::new(50., -50.), 10., true, true);
+        assert_eq!(step, EnemyStep::AttackObelisk);
+
+        // at the end of the path there is nowhere left to go
+        enemy.waypoint = 3;
+        let step = enemy_step(&lane, &enemy, Vec2::new(100., -100.), 10., false, false);
+        assert_eq!(step, EnemyStep::Wait);
+    }
 }
@@ -1,13 +1,33 @@
+use spectre_animations::prelude::{
+    AnimationClip, AnimationStateMachine, AnimationTransition, LoopMode,
+};
 use spectre_core::*;
 
-use crate::{components::*, constants::MELEE_RANGE};
+use crate::{
+    abilities::{ability_data::AbilityDatabase, AbilityDetail, BuffType},
+    components::*,
+    constants::MELEE_RANGE,
+    hero_classes::{HeroClass, HeroClassDefinition},
+};
 
-pub fn get_player(player_id: u8, lane: usize) -> PlayerBundle {
-    PlayerBundle {
-        health: Health::new(100., 0.),
-        mana: Mana::new(200.),
+/// builds a hero from their class. Starting abilities fill their action slot, and the buffs
+/// from passive starting abilities are applied straight away
+pub fn get_player(
+    player_id: u8,
+    lane: usize,
+    class_id: &str,
+    class: &HeroClassDefinition,
+    hotkeys: Vec<String>,
+    abilities: &AbilityDatabase,
+) -> PlayerBundle {
+    let attack = &class.attack;
+    let defence = &class.defence;
+
+    let mut bundle = PlayerBundle {
+        health: Health::new(class.health, class.health_regen),
+        mana: Mana::new(class.mana),
         movement: Movement {
-            movement_speed: BuffableStatistic::new(75.),
+            movement_speed: BuffableStatistic::new(class.movement_speed),
         },
         player: Player {
             player_id,
@@ -15,18 +35,33 @@ pub fn get_player(player_id: u8, lane: usize) -> PlayerBundle {
             target_lane: lane,
             is_moving: false,
             abilities: vec![],
+            targeting_policy: TargetingPolicy::default(),
+        },
+        class: HeroClass {
+            id: class_id.to_string(),
+            ability_tree: class.ability_tree,
+            hotkeys,
         },
         attack: BaseAttack {
-            attack_range: MELEE_RANGE,
-            attack_speed: BuffableStatistic::new(1.5),
-            min_attack_damage: 10,
-            max_attack_damage: 15,
-            crit_chance: 0.05,
+            attack_range: attack.range.unwrap_or(MELEE_RANGE),
+            attack_speed: BuffableStatistic::new(attack.speed),
+            min_attack_damage: attack.min_damage,
+            max_attack_damage: attack.max_damage,
+            damage_type: attack.damage_type,
+            crit_chance: attack.crit_chance,
+            projectile: attack.projectile.clone(),
+            wind_up: attack.wind_up,
             ..Default::default()
         },
         defence: Defence {
-            base_armour: BuffableStatistic::new(1.),
-            ..Default::default()
+            base_armour: BuffableStatistic::new(defence.armour),
+            fire_armour: defence.fire_armour,
+            electricity_armour: defence.electricity_armour,
+            poison_armour: defence.poison_armour,
+            frost_armour: defence.frost_armour,
+            resistances: defence.resistances.clone(),
+            dodge_chance: defence.dodge_chance,
+            block_chance: defence.block_chance,
         },
         actions: PlayerAbilityActions {
             actions: vec![
@@ -39,6 +74,177 @@ pub fn get_player(player_id: u8, lane: usize) -> PlayerBundle {
                     next_available: f32::MAX,
                 },
             ],
-        }, // attack_target: AttackTarget::default(),
+        },
+        attack_target: AttackTarget::default(),
+    };
+
+    for id in class.starting_abilities.iter() {
+        let ability = match abilities.abilities.get(id) {
+            None => {
+                println!("Unknown starting ability {} for {}, skipping", id, class_id);
+                continue;
+            }
+            Some(ability) => ability,
+        };
+
+        bundle.player.abilities.push(ability.id);
+
+        if !ability.passive {
+            let slot = ability
+                .slot_number
+                .checked_sub(1)
+                .and_then(|idx| bundle.actions.actions.get_mut(idx));
+            match slot {
+                None => println!(
+                    "Starting ability {} for {} has no action slot {}, not equipping it",
+                    ability.id, class_id, ability.slot_number
+                ),
+                Some(slot) => {
+                    slot.action = Some(ability.id);
+                    slot.next_available = 0.;
+                }
+            };
+        }
+
+        for effect in ability.effects.iter() {
+            match effect {
+                AbilityDetail::Buff(detail) => match detail.buff_type {
+                    BuffType::Armour => bundle.defence.base_armour.add_buff(detail.buff),
+                    BuffType::Health => {
+                        bundle.health.max_health.add_buff(detail.buff);
+                        bundle.health.current_health = bundle.health.max_health.value;
+                        bundle.health.target_health = bundle.health.current_health;
+                    }
+                    BuffType::Mana => {
+                        bundle.mana.max_mana.add_buff(detail.buff);
+                        bundle.mana.current_mana = bundle.mana.max_mana.value;
+                    }
+                    BuffType::Regeneration => bundle.health.regeneration.add_buff(detail.buff),
+                    BuffType::MovementSpeed => bundle.movement.movement_speed.add_buff(detail.buff),
+                },
+                _ => {}
+            };
+        }
+    }
+
+    bundle
+}
+
+/// The clips used by the player animation graph. The character sheets only have "idle" and
+/// "dead" so far, so any of the attack, hurt and stunned clips missing from the sheet reuse
+/// the idle frames
+pub fn get_player_clips(sheet_clips: Vec<AnimationClip>) -> Vec<AnimationClip> {
+    let idle = match sheet_clips.iter().find(|clip| clip.name == "idle") {
+        None => return sheet_clips,
+        Some(idle) => idle.clone(),
+    };
+
+    let mut clips = sheet_clips;
+    for (name, loop_mode) in [
+        ("attack", LoopMode::Once),
+        ("hurt", LoopMode::Once),
+        ("stunned", LoopMode::Loop),
+    ]
+    .iter()
+    {
+        if clips.iter().any(|clip| clip.name == *name) {
+            continue;
+        }
+
+        clips.push(AnimationClip {
+            name: name.to_string(),
+            loop_mode: *loop_mode,
+            ..idle.clone()
+        });
+    }
+
+    clips
+}
+
+/// The animation graph for a player. The "moving", "stunned" and "incapacitated" parameters
+/// choose the state, and the "attack" and "hurt" triggers play their clip once before
+/// returning to idle
+pub fn get_player_animations() -> AnimationStateMachine {
+    AnimationStateMachine::new("idle")
+        .with_state("moving", "idle") // no walk cycle in the character sheets yet
+        .with_uninterruptible_state("attacking", "attack")
+        .with_uninterruptible_state("hurt", "hurt")
+        .with_state("stunned", "stunned")
+        .with_state("dead", "dead")
+        .with_transition(
+            AnimationTransition::from_any("dead")
+                .when("incapacitated")
+                .interrupting(),
+        )
+        .with_transition(AnimationTransition::new("dead", "idle").when_not("incapacitated"))
+        .with_transition(
+            AnimationTransition::from_any("stunned")
+                .when("stunned")
+                .when_not("incapacitated")
+                .interrupting(),
+        )
+        .with_transition(AnimationTransition::new("stunned", "idle").when_not("stunned"))
+        .with_transition(
+            AnimationTransition::from_any("hurt")
+                .on_trigger("hurt")
+                .when_not("incapacitated")
+                .when_not("stunned"),
+        )
+        .with_transition(AnimationTransition::new("hurt", "idle").on_finish())
+        .with_transition(
+            AnimationTransition::from_any("attacking")
+                .on_trigger("attack")
+                .when_not("incapacitated")
+                .when_not("stunned"),
+        )
+        .with_transition(AnimationTransition::new("attacking", "idle").on_finish())
+        .with_transition(AnimationTransition::new("idle", "moving").when("moving"))
+        .with_transition(AnimationTransition::new("moving", "idle").when_not("moving"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_animations_follow_gameplay() {
+        let mut machine = get_player_animations();
+        assert_eq!(machine.evaluate(false), Some("idle".to_string()));
+
+        // swings and hits play once, then return to idle
+        machine.trigger("attack");
+        assert_eq!(machine.evaluate(false), Some("attack".to_string()));
+        machine.trigger("hurt");
+        assert_eq!(machine.evaluate(false), None);
+        assert_eq!(machine.evaluate(true), Some("idle".to_string()));
+        machine.trigger("hurt");
+        assert_eq!(machine.evaluate(false), Some("hurt".to_string()));
+
+        // stuns cut the hurt clip short and block attacks until they wear off
+        machine.set("stunned", true);
+        assert_eq!(machine.evaluate(false), Some("stunned".to_string()));
+        machine.trigger("attack");
+        assert_eq!(machine.evaluate(false), None);
+        machine.set("stunned", false);
+        assert_eq!(machine.evaluate(false), Some("idle".to_string()));
+
+        // dead heroes don't flinch
+        machine.set("incapacitated", true);
+        assert_eq!(machine.evaluate(false), Some("dead".to_string()));
+        machine.trigger("hurt");
+        assert_eq!(machine.evaluate(false), None);
+    }
+
+    #[test]
+    fn fills_in_missing_clips_from_idle() {
+        let clips = get_player_clips(vec![
+            AnimationClip::new("idle", 0, 1, 0.2, LoopMode::Loop),
+            AnimationClip::new("dead", 2, 3, 0.2, LoopMode::Loop),
+        ]);
+
+        let names: Vec<&str> = clips.iter().map(|clip| clip.name.as_str()).collect();
+        assert_eq!(names, vec!["idle", "dead", "attack", "hurt", "stunned"]);
+        assert_eq!(clips[2].first_frame, 0);
+        assert_eq!(clips[2].loop_mode, LoopMode::Once);
     }
 }
@@ -2,7 +2,7 @@
 // possible there are better ways to do this sort of thing. I'm going for dev speed here,
 // not nice code /shrug
 
-use crate::{assets::MaterialsAndTextures, events::EndOfDayEvent};
+use crate::{assets::MaterialsAndTextures, events::EndOfDayEvent, map::MapDefinition};
 use crate::{components::*, constants::*, game_scenes::MyGameScenes};
 use bevy::prelude::*;
 use spectre_core::{Health, Mana};
@@ -149,7 +149,33 @@ pub fn spawn_player_ui(
                         .with(PlayerLaneChangeLink {
                             player_id,
                             delta: 1,
-                        });
+                        })
+                        .spawn(ButtonComponents {
+                            style: Style {
+                                size: Size::new(Val::Px(140.0), Val::Px(32.0)),
+                                margin: Rect {
+                                    left: Val::Px(10.),
+                                    ..Default::default()
+                                },
+                                // horizontally center child text
+                                justify_content: JustifyContent::Center,
+                                // vertically center child text
+                                align_items: AlignItems::Center,
+                                ..Default::default()
+                            },
+                            material: assets.button_material,
+                            ..Default::default()
+                        })
+                        .with_children(|button_parent| {
+                            button_parent
+                                .spawn(text(
+                                    assets.main_font,
+                                    TargetingPolicy::default().name().to_string(),
+                                    10.,
+                                ))
+                                .with(PlayerTargetingLink { player_id });
+                        })
+                        .with(PlayerTargetingLink { player_id });
                 })
                 .spawn(text(assets.main_font, "  ".to_string(), 32.));
         })
@@ -259,6 +285,7 @@ pub fn update_player_mana_ui(
 pub fn player_lane_change_interaction(
     audio: ResMut<AudioOutput>,
     assets: Res<MaterialsAndTextures>,
+    map: Res<MapDefinition>,
     mut interaction_query: Query<(&Button, Mutated<Interaction>, &PlayerLaneChangeLink)>,
     mut player_query: Query<&mut Player>,
 ) {
@@ -273,7 +300,7 @@ pub fn player_lane_change_interaction(
                 for mut player in &mut player_query.iter() {
                     if player.player_id == link.player_id {
                         let new_lane = player.target_lane as i8 + link.delta;
-                        if new_lane < MIN_LANE as i8 || new_lane > MAX_LANE as i8 {
+                        if new_lane < 0 || !map.is_lane(new_lane as usize) {
                             println!(" --> Invalid request, ignoring lane change");
                         } else {
                             player.target_lane = new_lane as usize;
@@ -288,12 +315,56 @@ pub fn player_lane_change_interaction(
     }
 }
 
+/// Cycles a player's targeting policy, they pick a new target with the next attack
+pub fn player_targeting_interaction(
+    mut interaction_query: Query<(&Button, Mutated<Interaction>, &PlayerTargetingLink)>,
+    mut player_query: Query<(&mut Player, &mut AttackTarget)>,
+) {
+    for (_, interaction, link) in &mut interaction_query.iter() {
+        match *interaction {
+            Interaction::Clicked => {
+                for (mut player, mut target) in &mut player_query.iter() {
+                    if player.player_id == link.player_id {
+                        player.targeting_policy = player.targeting_policy.next();
+                        target.entity = None;
+                        println!(
+                            "Player {} now targeting {}",
+                            player.player_id,
+                            player.targeting_policy.name()
+                        );
+                        break;
+                    }
+                }
+            }
+            _ => {}
+        };
+    }
+}
+
+/// Shows each player's targeting policy on their targeting button
+pub fn update_player_targeting_ui(
+    mut text_query: Query<(&PlayerTargetingLink, &mut Text)>,
+    mut players: Query<&Player>,
+) {
+    for (link, mut txt) in &mut text_query.iter() {
+        for player in &mut players.iter() {
+            if player.player_id == link.player_id {
+                txt.value = player.targeting_policy.name().to_string();
+                break;
+            }
+        }
+    }
+}
+
 pub fn update_obelisk_status_text(
     player_score: Res<PlayerScore>,
     _obelisk: &ObeliskStatusTextUiLink,
     mut text: Mut<Text>,
 ) {
-    text.value = format!("Obelisk health {} / 1000", player_score.obelisk_health);
+    text.value = format!(
+        "Obelisk health {} / {}",
+        player_score.obelisk_health, player_score.max_obelisk_health
+    );
 }
 
 pub fn game_over_trigger(
@@ -352,7 +423,7 @@ pub fn close_ability_screen(
 
                 current_wave.wave_idx = 0;
                 current_wave.next_wave_time = game_time.elapsed_time + 2.;
-                player_score.obelisk_health = 1000;
+                player_score.obelisk_health = player_score.max_obelisk_health;
 
                 // regen all players, its a stuck in the loop theme if I recall :P
                 for mut health in &mut players.iter() {
@@ -379,6 +450,9 @@ pub fn abort_ability_screen(
             Interaction::Clicked => {
                 println!("Aborting game");
                 player_score.game_over = true;
+
+                // endless runs reach this screen with the obelisk still standing
+                player_score.obelisk_health = 0;
                 game_state.set_transition(MyGameScenes::Game);
             }
             _ => {}
@@ -0,0 +1,320 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use spectre_combat::damage::{AttackStats, CombatCalculator, DamageType};
+use spectre_core::Health;
+use spectre_time::GameTime;
+
+use crate::{
+    assets::MaterialsAndTextures,
+    combat_log::{CombatEvent, Combatant},
+    components::*,
+    constants::GAME_ELEMENT_LAYER,
+    floating_text::FloatingTextEvent,
+};
+
+/// How close a projectile has to get to a target to hit it
+const PROJECTILE_HIT_RADIUS: f32 = 16.;
+
+/// Projectiles are removed once they have flown this many times the distance to their target
+const PROJECTILE_MAX_FLIGHT: f32 = 1.5;
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_event::<FireProjectileEvent>()
+            .init_resource::<FireProjectileEventListener>()
+            .add_system(fire_projectile_system.system())
+            .add_system(projectile_system.system());
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ProjectileFlight {
+    /// flies towards where the target was when the projectile was fired
+    Straight,
+    /// follows the target until it hits
+    Homing,
+}
+
+/// Describes the projectile fired by a ranged attack
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProjectileSpec {
+    pub speed: f32,
+    pub flight: ProjectileFlight,
+
+    /// the number of extra targets the projectile can pass through
+    #[serde(default)]
+    pub pierce: u32,
+
+    /// damages every target within this radius of the impact, or 0 for a single target
+    #[serde(default)]
+    pub splash_radius: f32,
+
+    /// the scale of the projectile sprite
+    pub scale: f32,
+}
+
+/// A copy of the attacker's stats when the projectile was fired
+#[derive(Clone, Debug)]
+pub struct ProjectileDamage {
+    pub damage_range: (i32, i32),
+    pub damage_type: DamageType,
+    pub crit_chance: f32,
+    pub bonus_damage: Vec<(DamageType, i32)>,
+}
+
+impl ProjectileDamage {
+    pub fn from_stats(stats: &dyn AttackStats) -> Self {
+        ProjectileDamage {
+            damage_range: stats.damage_range(),
+            damage_type: stats.damage_type(),
+            crit_chance: stats.crit_chance(),
+            bonus_damage: stats.bonus_damage(),
+        }
+    }
+}
+
+impl AttackStats for ProjectileDamage {
+    fn damage_range(&self) -> (i32, i32) {
+        self.damage_range
+    }
+
+    fn damage_type(&self) -> DamageType {
+        self.damage_type
+    }
+
+    fn crit_chance(&self) -> f32 {
+        self.crit_chance
+    }
+
+    fn bonus_damage(&self) -> Vec<(DamageType, i32)> {
+        self.bonus_damage.clone()
+    }
+}
+
+/// Send to fire a projectile from a ranged attack
+pub struct FireProjectileEvent {
+    pub attacker: Combatant,
+    pub damage: ProjectileDamage,
+    pub spec: ProjectileSpec,
+    pub from: Vec3,
+    pub target: Entity,
+    pub target_position: Vec3,
+}
+
+impl FireProjectileEvent {
+    pub fn new(
+        attacker: Combatant,
+        attack: &BaseAttack,
+        spec: &ProjectileSpec,
+        from: Vec3,
+        target: Entity,
+        target_position: Vec3,
+    ) -> Self {
+        FireProjectileEvent {
+            attacker,
+            damage: ProjectileDamage::from_stats(attack),
+            spec: spec.clone(),
+            from,
+            target,
+            target_position,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct FireProjectileEventListener {
+    pub fire_reader: EventReader<FireProjectileEvent>,
+}
+
+/// A projectile in flight. Damage is resolved when it hits something
+pub struct Projectile {
+    pub attacker: Combatant,
+    pub damage: ProjectileDamage,
+    pub spec: ProjectileSpec,
+    pub target: Entity,
+    pub direction: Vec2,
+
+    /// how many more targets the projectile can pass through
+    pub pierce_remaining: u32,
+
+    /// the targets that have already been hit, so piercing projectiles only hit each once
+    pub hit: Vec<Entity>,
+    pub expires_at: f32,
+}
+
+impl Projectile {
+    /// heroes fire at enemies and everything else fires at heroes
+    fn hits_enemies(&self) -> bool {
+        match self.attacker {
+            Combatant::Hero(_) => true,
+            _ => false,
+        }
+    }
+}
+
+fn fire_projectile_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    assets: Res<MaterialsAndTextures>,
+    events: Res<Events<FireProjectileEvent>>,
+    mut listener: ResMut<FireProjectileEventListener>,
+) {
+    for event in listener.fire_reader.iter(&events) {
+        let offset = (event.target_position - event.from).truncate();
+        let distance = offset.length();
+        let direction = if distance > 0. {
+            offset / distance
+        } else {
+            Vec2::new(0., -1.)
+        };
+
+        commands
+            .spawn(SpriteComponents {
+                material: assets.boulder_material,
+                transform: Transform::from_translation(
+                    event.from.truncate().extend(GAME_ELEMENT_LAYER + 0.5),
+                )
+                .with_scale(event.spec.scale),
+                ..Default::default()
+            })
+            .with(Projectile {
+                attacker: event.attacker,
+                damage: event.damage.clone(),
+                spec: event.spec.clone(),
+                target: event.target,
+                direction,
+                pierce_remaining: event.spec.pierce,
+                hit: Vec::default(),
+                expires_at: game_time.elapsed_time
+                    + PROJECTILE_MAX_FLIGHT * (distance + PROJECTILE_HIT_RADIUS)
+                        / event.spec.speed.max(1.),
+            })
+            .with(GameSceneEntity);
+    }
+}
+
+/// Moves projectiles and resolves combat when they hit their targets
+fn projectile_system(
+    mut commands: Commands,
+    game_time: Res<GameTime>,
+    calculator: Res<CombatCalculator>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut combat_events: ResMut<Events<CombatEvent>>,
+    mut projectiles: Query<(Entity, &mut Projectile, &mut Transform)>,
+    mut heroes: Query<Without<Incapacitated, (Entity, &Player, &Transform, &mut Health, &Defence)>>,
+    mut enemies: Query<(Entity, &Enemy, &Transform, &mut Health, &Defence)>,
+) {
+    for (entity, mut projectile, mut transform) in &mut projectiles.iter() {
+        if projectile.expires_at <= game_time.elapsed_time {
+            commands.despawn(entity);
+            continue;
+        }
+
+        // find everything the projectile could hit
+        let candidates: Vec<(Entity, Combatant, Vec2)> = if projectile.hits_enemies() {
+            enemies
+                .iter()
+                .iter()
+                .filter(|(_, _, _, health, _)| health.target_health > 0.)
+                .map(|(e, _, tx, _, _)| (e, Combatant::Enemy(e), tx.translation().truncate()))
+                .collect()
+        } else {
+            heroes
+                .iter()
+                .iter()
+                .map(|(e, player, tx, _, _)| {
+                    (
+                        e,
+                        Combatant::Hero(player.player_id),
+                        tx.translation().truncate(),
+                    )
+                })
+                .collect()
+        };
+
+        // homing projectiles fly straight on if their target is gone
+        if projectile.spec.flight == ProjectileFlight::Homing {
+            match candidates.iter().find(|(e, _, _)| *e == projectile.target) {
+                None => {}
+                Some((_, _, target_position)) => {
+                    let offset = *target_position - transform.translation().truncate();
+                    if offset.length() > 0. {
+                        projectile.direction = offset / offset.length();
+                    }
+                }
+            };
+        }
+
+        let velocity = projectile.direction * projectile.spec.speed * game_time.delta;
+        transform.translate(velocity.extend(0.));
+        let position = transform.translation().truncate();
+
+        let impact = candidates.iter().find(|(e, _, target_position)| {
+            !projectile.hit.contains(e)
+                && (*target_position - position).length() <= PROJECTILE_HIT_RADIUS
+        });
+
+        if impact.is_none() {
+            continue;
+        }
+
+        // splash damage hits everything near the impact, including the target
+        let splash_radius = projectile.spec.splash_radius.max(PROJECTILE_HIT_RADIUS);
+        let struck: Vec<(Entity, Combatant, Vec3)> = if projectile.spec.splash_radius > 0. {
+            candidates
+                .iter()
+                .filter(|(e, _, target_position)| {
+                    !projectile.hit.contains(e)
+                        && (*target_position - position).length() <= splash_radius
+                })
+                .map(|(e, combatant, p)| (*e, *combatant, p.extend(0.)))
+                .collect()
+        } else {
+            impact
+                .iter()
+                .map(|(e, combatant, p)| (*e, *combatant, p.extend(0.)))
+                .collect()
+        };
+
+        for (target, combatant, target_position) in struck.into_iter() {
+            let (result, was_alive, is_alive) = match combatant {
+                Combatant::Hero(_) => {
+                    let defence = heroes.get::<Defence>(target).unwrap();
+                    let mut health = heroes.get_mut::<Health>(target).unwrap();
+                    let result = calculator.resolve(&projectile.damage, &*defence);
+                    let was_alive = health.target_health > 0.;
+                    health.target_health -= result.damage as f32;
+                    (result, was_alive, health.target_health > 0.)
+                }
+                _ => {
+                    let defence = enemies.get::<Defence>(target).unwrap();
+                    let mut health = enemies.get_mut::<Health>(target).unwrap();
+                    let result = calculator.resolve(&projectile.damage, &*defence);
+                    let was_alive = health.target_health > 0.;
+                    health.target_health -= result.damage as f32;
+                    (result, was_alive, health.target_health > 0.)
+                }
+            };
+
+            text_events.send(FloatingTextEvent::damage(&result, target_position));
+            combat_events.send(CombatEvent::damage(
+                game_time.elapsed_time,
+                projectile.attacker,
+                combatant,
+                None,
+                &result,
+                was_alive && !is_alive,
+            ));
+
+            projectile.hit.push(target);
+        }
+
+        if projectile.pierce_remaining == 0 {
+            commands.despawn(entity);
+        } else {
+            projectile.pierce_remaining -= 1;
+        }
+    }
+}
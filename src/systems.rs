@@ -1,22 +1,76 @@
 use bevy::prelude::*;
-use spectre_animations::AnimationState;
+use spectre_animations::prelude::AnimationStateMachine;
 use spectre_core::Health;
 
+use crate::combat_log::{CombatEvent, CombatEventKind, Combatant};
 use crate::components::*;
+use crate::floating_text::FloatingTextEvent;
+
+#[derive(Default)]
+pub struct HurtEventListener {
+    pub combat_reader: EventReader<CombatEvent>,
+}
+
+/// plays the hurt animation when a player takes damage
+pub fn player_hurt_system(
+    combat_events: Res<Events<CombatEvent>>,
+    mut listener: ResMut<HurtEventListener>,
+    mut players: Query<(&Player, &mut AnimationStateMachine)>,
+) {
+    let mut hurt: Vec<u8> = vec![];
+    for event in listener.combat_reader.iter(&combat_events) {
+        match (&event.target, &event.kind) {
+            (Combatant::Hero(player_id), CombatEventKind::Damage { result, .. }) => {
+                if result.damage > 0 {
+                    hurt.push(*player_id);
+                }
+            }
+            _ => {}
+        };
+    }
+
+    if hurt.is_empty() {
+        return;
+    }
+
+    for (player, mut animations) in &mut players.iter() {
+        if hurt.contains(&player.player_id) {
+            animations.trigger("hurt");
+        }
+    }
+}
 
 /// incapcitates players and shows incap animation when they die
 pub fn player_incapacitation_system(
     mut commands: Commands,
-    mut players: Query<Without<Incapacitated, (Entity, &Player, &mut AnimationState, &Health)>>,
+    mut text_events: ResMut<Events<FloatingTextEvent>>,
+    mut players: Query<
+        Without<
+            Incapacitated,
+            (
+                Entity,
+                &Player,
+                &mut AnimationStateMachine,
+                &Health,
+                &Transform,
+                &mut BaseAttack,
+            ),
+        >,
+    >,
 ) {
-    for (ent, player, mut anim_state, health) in &mut players.iter() {
+    for (ent, player, mut animations, health, tx, mut attack) in &mut players.iter() {
         if health.target_health > 0. {
             continue;
         }
 
         println!("Disabling player {}", player.player_id);
         commands.insert_one(ent, Incapacitated::default());
-        anim_state.set_animation(1); // incapacitated animation
+        animations.set("incapacitated", true);
+        attack.interrupt();
+        text_events.send(FloatingTextEvent::status(
+            "Incapacitated!",
+            tx.translation(),
+        ));
     }
 }
 
@@ -25,7 +79,7 @@ pub fn player_revival_system(
     entity: Entity,
     player: &Player,
     incap: &Incapacitated,
-    mut anim_state: Mut<AnimationState>,
+    mut animations: Mut<AnimationStateMachine>,
     mut health: Mut<Health>,
 ) {
     if !incap.is_revived {
@@ -37,7 +91,7 @@ pub fn player_revival_system(
     health.target_health = 0.5 * health.max_health.value;
     health.current_health = health.target_health;
 
-    anim_state.set_animation(0);
+    animations.set("incapacitated", false);
 
     commands.remove_one::<Incapacitated>(entity);
 }
@@ -1,98 +1,206 @@
 use crate::assets::MaterialsAndTextures;
 use crate::{
-    components::*, constants::*, enemy_factory::get_enemy_bundle, enemy_factory::EnemyType,
-    events::*, CurrentWave,
+    components::*,
+    constants::*,
+    difficulty::{Difficulty, DifficultyModifiers},
+    endless::GameMode,
+    enemy_behaviours::with_behaviours,
+    enemy_factory::{get_enemy_bundle, EnemyDatabase, EnemyDefinition},
+    events::*,
+    map::MapDefinition,
+    CurrentWave,
 };
 use bevy::prelude::*;
-use spectre_animations::spawn_animated_spritesheet;
+use serde::{Deserialize, Serialize};
+use spectre_animations::{spawn_animated_spritesheet, AnimationLibrary};
+use spectre_loaders::data_loaders::MergeData;
 use spectre_random::RNG;
 use spectre_time::GameTime;
 
-/// Defines the enemy waves in the game. Waves are stored by monster type, in tuples of tuples
-/// e.g. wolves might be item 1 in the top level tuple. The number of wolves to spawn is given
-/// by the inner tuple values, e.g. ((lane 1 count, lane 2 count, lane 3 count), ...)
+/// The wave files to load. Waves from later files are played after earlier ones
+pub const WAVE_DATA_FILES: [&str; 1] = ["assets/data/waves.wvr"];
 
-#[derive(Copy, Clone)]
-pub struct Wave([isize; 3]);
+/// How the enemies in a spawn group are spread out over time and lanes
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub enum SpawnPattern {
+    /// one enemy every interval
+    Trickle,
+    /// groups of `size` enemies arrive together, with the interval between each burst
+    Burst { size: usize },
+    /// one enemy every interval, cycling through the lanes instead of using the group's lane
+    Alternating { lanes: Vec<usize> },
+    /// one enemy every interval, into whichever lane has the fewest heroes when it spawns
+    FewestHeroes,
+}
 
-impl Default for Wave {
+impl Default for SpawnPattern {
     fn default() -> Self {
-        Wave([0, 0, 0])
+        SpawnPattern::Trickle
     }
 }
 
-pub struct WaveData {
-    pub wolves: Wave,
-    pub bears: Wave,
+/// A number of enemies of one type spawned into a lane during a wave
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct SpawnGroup {
+    /// the enemy type id, i.e. "wolf"
+    pub enemy: String,
+    pub lane: usize,
+    pub count: usize,
+
+    /// seconds between each enemy (or burst) in the group
+    #[serde(default)]
+    pub interval: f32,
+
+    /// seconds after the start of the wave before the first enemy spawns
+    #[serde(default)]
+    pub delay: f32,
+
+    #[serde(default)]
+    pub pattern: SpawnPattern,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct WaveDefinition {
+    pub groups: Vec<SpawnGroup>,
+
+    /// seconds from the start of this wave until the next one
     pub post_wave_delay: f32,
 }
 
-pub const WAVE_DATA: [WaveData; 8] = [
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([0, 0, 0]),
-        post_wave_delay: 10.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([0, 1, 0]),
-        post_wave_delay: 12.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([0, 0, 0]),
-        post_wave_delay: 18.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([1, 1, 1]),
-        post_wave_delay: 15.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([1, 1, 1]),
-        post_wave_delay: 15.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([1, 1, 1]),
-        post_wave_delay: 14.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([1, 1, 1]),
-        post_wave_delay: 12.,
-    },
-    WaveData {
-        wolves: Wave([1, 1, 1]),
-        bears: Wave([1, 1, 1]),
-        post_wave_delay: 30.,
-    },
-];
+/// Defines the enemy waves in the game, loaded from the `.wvr` files in `assets/data`
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct WaveDatabase {
+    pub waves: Vec<WaveDefinition>,
+}
+
+impl MergeData for WaveDatabase {
+    fn merge(&mut self, other: &Self) {
+        self.waves.extend(other.waves.iter().cloned());
+    }
+}
+
+/// The lane a queued enemy will spawn in
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SpawnLane {
+    Fixed(usize),
+    /// picked when the enemy spawns
+    FewestHeroes,
+}
+
+impl SpawnLane {
+    /// the lane to spawn in, given the number of lanes and the lane of each active hero
+    pub fn resolve(&self, lane_count: usize, hero_lanes: &[usize]) -> usize {
+        match self {
+            SpawnLane::Fixed(lane) => *lane,
+            SpawnLane::FewestHeroes => {
+                let counts: Vec<usize> = (0..lane_count.max(1))
+                    .map(|lane| hero_lanes.iter().filter(|l| **l == lane).count())
+                    .collect();
+                let fewest = *counts.iter().min().unwrap();
+                let lanes: Vec<usize> = (0..counts.len())
+                    .filter(|lane| counts[*lane] == fewest)
+                    .collect();
+
+                lanes[RNG::usize_between(0, lanes.len())]
+            }
+        }
+    }
+}
+
+/// An enemy waiting to be spawned
+#[derive(Clone, Debug)]
+pub struct PendingSpawn {
+    /// the enemy id, i.e. "wolf"
+    pub enemy: String,
+    pub lane: SpawnLane,
+    pub spawn_at: f32,
+}
+
+/// A resource with the enemies from the current wave that haven't spawned yet
+#[derive(Default)]
+pub struct SpawnQueue {
+    pub pending: Vec<PendingSpawn>,
+}
+
+impl SpawnQueue {
+    /// queues every enemy in the wave, relative to the start time. Group sizes are scaled
+    /// by the difficulty
+    pub fn queue_wave(
+        &mut self,
+        wave: &WaveDefinition,
+        start_time: f32,
+        difficulty: &DifficultyModifiers,
+    ) {
+        for group in wave.groups.iter() {
+            for idx in 0..difficulty.group_size(group.count) {
+                let trickle_time = start_time + group.delay + group.interval * idx as f32;
+                let (spawn_at, lane) = match &group.pattern {
+                    SpawnPattern::Trickle => (trickle_time, SpawnLane::Fixed(group.lane)),
+                    SpawnPattern::Burst { size } => (
+                        start_time + group.delay + group.interval * (idx / (*size).max(1)) as f32,
+                        SpawnLane::Fixed(group.lane),
+                    ),
+                    SpawnPattern::Alternating { lanes } => match lanes.len() {
+                        0 => (trickle_time, SpawnLane::Fixed(group.lane)),
+                        n => (trickle_time, SpawnLane::Fixed(lanes[idx % n])),
+                    },
+                    SpawnPattern::FewestHeroes => (trickle_time, SpawnLane::FewestHeroes),
+                };
+
+                self.pending.push(PendingSpawn {
+                    enemy: group.enemy.clone(),
+                    lane,
+                    spawn_at,
+                });
+            }
+        }
+    }
+
+    /// removes and returns the enemies due to spawn
+    pub fn take_due(&mut self, elapsed_time: f32) -> Vec<PendingSpawn> {
+        let (due, pending): (Vec<PendingSpawn>, Vec<PendingSpawn>) = self
+            .pending
+            .drain(..)
+            .partition(|spawn| spawn.spawn_at <= elapsed_time);
+        self.pending = pending;
+        due
+    }
+
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
 
 fn spawn_enemy(
     mut commands: &mut Commands,
-    enemy_type: EnemyType,
+    animations: &Res<AnimationLibrary>,
+    definition: &EnemyDefinition,
+    map: &MapDefinition,
     lane: usize,
+    difficulty: &DifficultyModifiers,
     texture_atlas_handle: Handle<TextureAtlas>,
     health_bar_full: Handle<ColorMaterial>,
 ) {
-    let bundle = get_enemy_bundle(enemy_type, lane);
+    let mut bundle = get_enemy_bundle(definition, lane);
+    difficulty.apply_to_enemy(&mut bundle);
 
-    let spawned = spawn_animated_spritesheet(
+    // keep the same sideways jitter along the whole path
+    let jitter = RNG::f32_between(-10., 10.);
+    bundle.enemy.offset = Vec2::new(jitter, 0.);
+
+    spawn_animated_spritesheet(
         &mut commands,
         texture_atlas_handle,
-        0.3,
-        vec![(0, 3)],
-        Vec2::from(SPAWN_LOCATIONS[lane]).extend(GAME_ELEMENT_LAYER)
-            + Vec3::new(RNG::f32_between(-10., 10.), RNG::f32_between(-30., 0.), 0.),
-        false,
+        definition.select_clips(animations.clips(texture_atlas_handle)),
+        map.spawn_location(lane).extend(GAME_ELEMENT_LAYER)
+            + Vec3::new(jitter, RNG::f32_between(-30., 0.), 0.),
     )
-    // TODO enum to specify enemy type
     .with_bundle(bundle)
-    .with(GameSceneEntity)
-    .current_entity()
-    .unwrap();
+    .with(GameSceneEntity);
+
+    let spawned = with_behaviours(commands, definition)
+        .current_entity()
+        .unwrap();
 
     commands
         .spawn(SpriteComponents {
@@ -107,29 +215,95 @@ fn spawn_enemy(
 pub fn wave_spawning_system(
     mut commands: Commands,
     mut waves: ResMut<CurrentWave>,
+    mut spawn_queue: ResMut<SpawnQueue>,
+    mut mode: ResMut<GameMode>,
+    wave_data: Res<WaveDatabase>,
+    difficulty: Res<Difficulty>,
+    enemies: Res<EnemyDatabase>,
+    map: Res<MapDefinition>,
     assets: Res<MaterialsAndTextures>,
+    animations: Res<AnimationLibrary>,
     game_time: Res<GameTime>,
     mut wave_spawned: ResMut<Events<WaveSpawnedEvent>>,
     mut end_of_day: ResMut<Events<EndOfDayEvent>>,
+    mut players: Query<Without<Incapacitated, &Player>>,
 ) {
-    // not ready to spawn
-    if waves.next_wave_time > game_time.elapsed_time {
+    // game paused
+    if game_time.game_speed < 0.01 {
         return;
     }
 
-    // game paused
-    if game_time.game_speed < 0.01 {
+    let hero_lanes: Vec<usize> = players
+        .iter()
+        .iter()
+        .map(|player| player.current_lane)
+        .collect();
+
+    // endless runs generate the day's waves as it starts
+    match &mut *mode {
+        GameMode::Endless(run) => run.start_day(map.lane_count(), &hero_lanes, &enemies),
+        GameMode::Story => {}
+    }
+    let waves_today = mode.waves(&wave_data);
+    let modifiers = difficulty.modifiers();
+
+    for spawn in spawn_queue.take_due(game_time.elapsed_time).into_iter() {
+        let definition = match enemies.get(&spawn.enemy) {
+            None => {
+                println!("Unknown enemy type {} in wave, skipping", spawn.enemy);
+                continue;
+            }
+            Some(definition) => definition,
+        };
+
+        // waves can be written for maps with more lanes than this one
+        let wave_lane = spawn.lane.resolve(map.lane_count(), &hero_lanes);
+        let lane = map.wave_lane(wave_lane);
+        if lane != wave_lane {
+            println!(
+                "Map {} has no lane {}, spawning {} in lane {}",
+                map.name, wave_lane, spawn.enemy, lane
+            );
+        }
+
+        let atlas = match animations.atlas(&definition.sheet) {
+            None => {
+                println!(
+                    "Unknown sheet {} for enemy {}",
+                    definition.sheet, spawn.enemy
+                );
+                continue;
+            }
+            Some(atlas) => atlas,
+        };
+
+        spawn_enemy(
+            &mut commands,
+            &animations,
+            definition,
+            &map,
+            lane,
+            &modifiers,
+            atlas,
+            assets.healthbar_material,
+        );
+    }
+
+    // not ready to spawn
+    if waves.next_wave_time > game_time.elapsed_time {
         return;
     }
 
     // nothing else to spawn, just set to max to effectively disable this system
-    if waves.wave_idx >= WAVE_DATA.len() {
-        end_of_day.send(EndOfDayEvent(true));
-        waves.next_wave_time = f32::MAX;
+    if waves.wave_idx >= waves_today.len() {
+        if spawn_queue.pending.is_empty() {
+            end_of_day.send(EndOfDayEvent(true));
+            waves.next_wave_time = f32::MAX;
+        }
         return;
     }
 
-    let wave_to_spawn = &WAVE_DATA[waves.wave_idx];
+    let wave_to_spawn = &waves_today[waves.wave_idx];
     wave_spawned.send(WaveSpawnedEvent {
         wave_idx: waves.wave_idx,
     });
@@ -139,45 +313,96 @@ pub fn wave_spawning_system(
         waves.wave_idx, game_time.elapsed_time, waves.next_wave_time
     );
 
+    spawn_queue.queue_wave(wave_to_spawn, game_time.elapsed_time, &modifiers);
+
     waves.next_wave_time = game_time.elapsed_time + wave_to_spawn.post_wave_delay;
     waves.wave_idx += 1;
     println!(
         "Wave {} should spawn at {}",
         waves.wave_idx, waves.next_wave_time
     );
+}
 
-    // just hack it out for now
-    let wolves = wave_to_spawn.wolves.clone();
-    wolves
-        .0
-        .iter()
-        .enumerate()
-        .for_each(|(lane, &num_to_spawn)| {
-            for _ in 0..num_to_spawn {
-                spawn_enemy(
-                    &mut commands,
-                    EnemyType::Wolf,
-                    lane,
-                    assets.wolf_atlas,
-                    assets.healthbar_material,
-                );
-            }
-        });
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enemy_factory::ENEMY_DATA_FILES;
+    use spectre_loaders::data_loaders::load_merged;
 
-    let bears = wave_to_spawn.bears.clone();
-    bears
-        .0
-        .iter()
-        .enumerate()
-        .for_each(|(lane, &num_to_spawn)| {
-            for _ in 0..num_to_spawn {
-                spawn_enemy(
-                    &mut commands,
-                    EnemyType::Bear,
-                    lane,
-                    assets.bear_atlas,
-                    assets.healthbar_material,
-                );
+    #[test]
+    fn loads_the_wave_files() {
+        let database = load_merged::<WaveDatabase>(&WAVE_DATA_FILES);
+        assert_eq!(database.waves.len(), 8);
+        assert_eq!(database.waves[0].post_wave_delay, 10.);
+
+        let enemies = load_merged::<EnemyDatabase>(&ENEMY_DATA_FILES);
+        for wave in database.waves.iter() {
+            for group in wave.groups.iter() {
+                assert!(enemies.get(&group.enemy).is_some());
             }
-        });
+        }
+    }
+
+    #[test]
+    fn queues_groups_over_time() {
+        let wave = WaveDefinition {
+            groups: vec![SpawnGroup {
+                enemy: "wolf".to_string(),
+                lane: 1,
+                count: 3,
+                interval: 2.,
+                delay: 1.,
+                pattern: SpawnPattern::Trickle,
+            }],
+            post_wave_delay: 10.,
+        };
+
+        let mut queue = SpawnQueue::default();
+        queue.queue_wave(&wave, 10., &DifficultyModifiers::default());
+        assert_eq!(queue.take_due(10.).len(), 0);
+        assert_eq!(queue.take_due(11.).len(), 1);
+        assert_eq!(queue.take_due(15.).len(), 2);
+        assert!(queue.pending.is_empty());
+    }
+
+    fn group(count: usize, pattern: SpawnPattern) -> WaveDefinition {
+        WaveDefinition {
+            groups: vec![SpawnGroup {
+                enemy: "bear".to_string(),
+                lane: 0,
+                count,
+                interval: 5.,
+                delay: 0.,
+                pattern,
+            }],
+            post_wave_delay: 10.,
+        }
+    }
+
+    #[test]
+    fn spawn_patterns() {
+        let mut queue = SpawnQueue::default();
+        let normal = DifficultyModifiers::default();
+        queue.queue_wave(&group(4, SpawnPattern::Burst { size: 2 }), 0., &normal);
+        assert_eq!(queue.take_due(0.).len(), 2);
+        assert_eq!(queue.take_due(5.).len(), 2);
+
+        queue.queue_wave(
+            &group(3, SpawnPattern::Alternating { lanes: vec![2, 1] }),
+            0.,
+            &normal,
+        );
+        let lanes: Vec<SpawnLane> = queue.take_due(10.).iter().map(|s| s.lane).collect();
+        assert_eq!(
+            lanes,
+            vec![
+                SpawnLane::Fixed(2),
+                SpawnLane::Fixed(1),
+                SpawnLane::Fixed(2)
+            ]
+        );
+
+        assert_eq!(SpawnLane::FewestHeroes.resolve(3, &[0, 0, 2]), 1);
+        assert_eq!(SpawnLane::FewestHeroes.resolve(4, &[0, 1, 1, 2, 2]), 3);
+    }
 }